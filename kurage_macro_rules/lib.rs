@@ -3,9 +3,172 @@ pub use relm4;
 #[cfg(feature = "tracing")]
 pub use tracing;
 
+#[doc(hidden)]
+#[path = "private.rs"]
+pub mod __private;
+
+/// Generate the Page enum and the model of an app made of a sequence of pages.
+///
+/// Each `NN: Name` entry is a page, whose component `NamePage` is imported from
+/// `pages::_NN_name` along with its `NamePageOutput`. An entry may be followed by an expression
+/// to forward the outputs of the page with, which defaults to mapping `Nav(action)` to
+/// `AppMsg::Nav(action)`.
+///
+/// A page may be skipped depending on the state of the app by writing `#[skip_if(predicate)]`
+/// before its name, where the predicate is a `fn(&AppModel) -> bool`, usually a closure. The
+/// state of the pages is available through their controllers, e.g.
+/// `|model| model.network_page.model().online`.
+///
+/// The macro generates:
+/// - the `Page` enum, with a variant per page in order, `first()`, `last()`, `next()` and
+///   `prev()`, and conversions from and to `usize` using the `NN` numbers. The numbers don't
+///   need to follow each other, e.g. to leave room for new pages: `next()` and `prev()` follow
+///   the order of the entries
+/// - `Page::ALL`, `Page::COUNT` and `Page::iter()` listing the pages, and `Page::name()`, the
+///   name of the page in snake case, which is also used by `Display` and `FromStr`
+/// - the nav action enum, with `Next`, `Back`, `GoTo(Page)` and `Quit`, to be sent by the pages.
+///   It is named after the `Page` enum, e.g. `PageNavAction` for `generate_pages!(Page …)` or
+///   `StepNavAction` for `generate_pages!(Step …)`, so that it doesn't clash with a `NavAction`
+///   of the app
+/// - the `AppModel` struct, holding the current `page` and the controller of each page in a
+///   `name_page` field, with `AppModel::_default(sender)` launching the pages and
+///   `AppModel::navigate(action)` to move between them. `Quit` is left to the app, which can
+///   e.g. ask to save changes first: `navigate()` returns `ControlFlow::Break(())` for it
+/// - `AppModel::next_page()` and `AppModel::prev_page()`, the neighbouring pages that aren't
+///   skipped, `AppModel::is_skipped(page)`, `AppModel::pages()` listing the pages that aren't
+///   skipped, and `AppModel::progress()`, the position of the current page among them and their
+///   number
+/// - a history of the pages visited, so that `Back` returns to the page the current one was
+///   actually reached from, also available as `AppModel::go_back()` and
+///   `AppModel::can_go_back()`. `GoTo(page)` truncates the history back to `page` if it was
+///   visited
+/// - `AppModel::stack(transition, duration)`, a new `gtk::Stack` with the widgets of the pages
+///   named after their `name()`, and `AppModel::fill_stack(stack)` to add them to an existing
+///   stack. The stack then shows the current page, as `navigate()` and `go_back()` keep it in
+///   sync. Filling another stack moves the pages into it, and only the last one is kept in sync
+///
+/// You still have to implement the component for `AppModel`, with `AppMsg` as its input.
+///
+/// # Examples
+///
+/// ```
+/// use kurage::relm4::{self, prelude::*};
+/// use kurage::relm4::gtk::{self, prelude::*};
+///
+/// mod pages {
+///   pub mod _00_welcome {
+///     use crate::PageNavAction;
+///     use kurage::relm4::gtk::{self, prelude::*};
+///     kurage::generate_component!(WelcomePage:
+///       update(self, message, sender) {
+///         Start => emit Nav(PageNavAction::Next),
+///       } => { Nav(PageNavAction) }
+///
+///       gtk::Button {
+///         set_label: "Start",
+///         connect_clicked => WelcomePageMsg::Start,
+///       }
+///     );
+///   }
+///   pub mod _10_network {
+///     use crate::PageNavAction;
+///     use kurage::relm4::gtk::{self, prelude::*};
+///     kurage::generate_component!(NetworkPage {
+///       pub online: bool,
+///     }:
+///       update(self, message, sender) {
+///         Connected => {
+///           self.online = true;
+///           emit Nav(PageNavAction::Next);
+///         },
+///       } => { Nav(PageNavAction) }
+///
+///       gtk::Button {
+///         set_label: "Connect",
+///         connect_clicked => NetworkPageMsg::Connected,
+///       }
+///     );
+///   }
+///   pub mod _20_done {
+///     use crate::PageNavAction;
+///     use kurage::relm4::gtk::{self, prelude::*};
+///     kurage::generate_component!(DonePage:
+///       update(self, message, sender) {
+///         Back => emit Nav(PageNavAction::Back),
+///         Close => emit Nav(PageNavAction::Quit),
+///       } => { Nav(PageNavAction) }
+///
+///       gtk::Box {
+///         gtk::Button {
+///           set_label: "Back",
+///           connect_clicked => DonePageMsg::Back,
+///         },
+///         gtk::Button {
+///           set_label: "Close",
+///           connect_clicked => DonePageMsg::Close,
+///         },
+///       }
+///     );
+///   }
+/// }
+///
+/// kurage::generate_pages!(Page AppModel AppMsg:
+///   00: Welcome,
+///   10: #[skip_if(|model| model.network_page.model().online)] Network,
+///   20: Done,
+/// );
+///
+/// #[derive(Debug)]
+/// pub enum AppMsg {
+///   Nav(PageNavAction),
+/// }
+///
+/// #[relm4::component(pub)]
+/// impl SimpleComponent for AppModel {
+///   type Init = ();
+///   type Input = AppMsg;
+///   type Output = ();
+///
+///   view! {
+///     gtk::Window {
+///       #[local_ref]
+///       stack -> gtk::Stack {}
+///     }
+///   }
+///
+///   fn init((): (), root: Self::Root, sender: ComponentSender<Self>) -> ComponentParts<Self> {
+///     let model = Self::_default(sender);
+///     let stack = &model.stack(gtk::StackTransitionType::SlideLeftRight, 200);
+///     let widgets = view_output!();
+///     ComponentParts { model, widgets }
+///   }
+///
+///   fn update(&mut self, message: AppMsg, _sender: ComponentSender<Self>) {
+///     match message {
+///       AppMsg::Nav(action) => {
+///         if self.navigate(action).is_break() {
+///           relm4::main_application().quit();
+///         }
+///       }
+///     }
+///   }
+/// }
+///
+/// # fn main() {
+/// assert_eq!(Page::first().next(), Some(Page::Network));
+/// assert_eq!(Page::last().prev(), Some(Page::Network));
+/// assert_eq!(Page::last().next(), None);
+/// assert_eq!(Page::Done.prev().and_then(Page::prev), Some(Page::Welcome));
+/// assert_eq!((usize::from(Page::Network), Page::try_from(20)), (10, Ok(Page::Done)));
+/// assert_eq!(Page::try_from(1), Err(()));
+/// assert_eq!(Page::iter().map(Page::name).collect::<Vec<_>>(), ["welcome", "network", "done"]);
+/// assert_eq!("done".parse(), Ok(Page::Done));
+/// assert_eq!(Page::Welcome.to_string(), "welcome");
+/// # }
+/// ```
 #[macro_export]
 macro_rules! generate_pages {
-    ($Page:ident $AppModel:ident $AppMsg:ident: $($num:tt: $page:ident $($forward:expr)?),+$(,)?) => { $crate::paste::paste! {
+    ($Page:ident $AppModel:ident $AppMsg:ident: $($num:tt: $(#[skip_if($skip:expr)])? $page:ident $($forward:expr)?),+$(,)?) => { $crate::paste::paste! {
         use pages::{$([<_$num _$page:lower>]::[<$page:camel Page>]),+};
         use pages::{$([<_$num _$page:lower>]::[<$page:camel PageOutput>]),+};
 
@@ -16,6 +179,60 @@ macro_rules! generate_pages {
             $([< $page:camel >]),+
         }
 
+        /// Sent by the pages in `Nav(…)` outputs to move between them.
+        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+        pub enum [<$Page NavAction>] {
+            Next,
+            Back,
+            GoTo($Page),
+            Quit,
+        }
+
+        impl $Page {
+            /// All the pages, in order.
+            pub const ALL: [Self; Self::COUNT] = [$(Self::[<$page:camel>]),+];
+            /// The number of pages.
+            pub const COUNT: usize = [$(::core::stringify!($page)),+].len();
+
+            /// Iterate over all the pages, in order.
+            pub fn iter() -> ::core::array::IntoIter<Self, { Self::COUNT }> {
+                Self::ALL.into_iter()
+            }
+
+            /// The name of the page in snake case, e.g. for the children of a `gtk::Stack`.
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $( Self::[<$page:camel>] => ::core::stringify!([<$page:snake>]), )+
+                }
+            }
+
+            /// The first page, which is also the default.
+            pub const fn first() -> Self {
+                Self::ALL[0]
+            }
+
+            /// The last page.
+            pub const fn last() -> Self {
+                Self::ALL[Self::COUNT - 1]
+            }
+
+            /// The position of the page in [`Self::ALL`], regardless of its number.
+            const fn position(self) -> usize {
+                // the variants are declared in the order of `ALL`
+                self as usize
+            }
+
+            /// The page after this one, if any.
+            pub fn next(self) -> Option<Self> {
+                Self::ALL.get(self.position() + 1).copied()
+            }
+
+            /// The page before this one, if any.
+            pub fn prev(self) -> Option<Self> {
+                (self.position().checked_sub(1)).and_then(|i| Self::ALL.get(i).copied())
+            }
+        }
+
         impl TryFrom<usize> for $Page {
             type Error = ();
 
@@ -27,6 +244,21 @@ macro_rules! generate_pages {
                 })
             }
         }
+        impl ::core::fmt::Display for $Page {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        impl ::core::str::FromStr for $Page {
+            type Err = ();
+
+            /// Parse the [`name()`](Self::name) of a page.
+            fn from_str(s: &str) -> Result<Self, ()> {
+                Self::iter().find(|page| page.name() == s).ok_or(())
+            }
+        }
+
         impl From<$Page> for usize {
             fn from(val: $Page) -> Self {
                 #[allow(clippy::zero_prefixed_literal)]
@@ -39,14 +271,147 @@ macro_rules! generate_pages {
         #[derive(Debug)]
         pub struct $AppModel {
             page: $Page,
+            /// The pages the current one was reached from, the last one being the previous page.
+            history: Vec<$Page>,
+            /// The stack last given to `fill_stack()`, kept showing the current page.
+            stack: ::core::cell::RefCell<Option<$crate::relm4::gtk::Stack>>,
             $(
                 pub [<$page:snake _page>]: $crate::relm4::Controller<[<$page:camel Page>]>,
             )+
         }
 
         impl $AppModel {
+            /// Whether `page` is skipped by `Next`, according to its `#[skip_if]`.
+            pub fn is_skipped(&self, page: $Page) -> bool {
+                match page {
+                    $( $Page::[<$page:camel>] => $crate::generate_pages!(@skip_if self $($skip)?), )+
+                }
+            }
+
+            /// The pages that aren't skipped, in order.
+            pub fn pages(&self) -> impl Iterator<Item = $Page> + '_ {
+                $Page::iter().filter(|page| !self.is_skipped(*page))
+            }
+
+            /// The next page that isn't skipped, if any.
+            pub fn next_page(&self) -> Option<$Page> {
+                ::core::iter::successors(self.page.next(), |page| page.next())
+                    .find(|page| !self.is_skipped(*page))
+            }
+
+            /// The previous page that isn't skipped, if any.
+            pub fn prev_page(&self) -> Option<$Page> {
+                ::core::iter::successors(self.page.prev(), |page| page.prev())
+                    .find(|page| !self.is_skipped(*page))
+            }
+
+            /// The number of pages before the current one and the total number of pages, not
+            /// counting the skipped ones, e.g. for "step 2 of 5".
+            pub fn progress(&self) -> (usize, usize) {
+                let before = $Page::iter()
+                    .take_while(|page| *page != self.page)
+                    .filter(|page| !self.is_skipped(*page))
+                    .count();
+                (before, self.pages().count())
+            }
+
+            /// Whether there is a page to go back to.
+            pub fn can_go_back(&self) -> bool {
+                !self.history.is_empty()
+            }
+
+            /// Go back to the page the current one was reached from, returning whether there was
+            /// one.
+            pub fn go_back(&mut self) -> bool {
+                let Some(page) = self.history.pop() else {
+                    return false;
+                };
+                self.page = page;
+                self.sync_stack();
+                true
+            }
+
+            /// Move to another page, keeping track of the pages visited.
+            ///
+            /// - `Next` jumps over the skipped pages, and stays on the current one if there are no
+            ///   pages left.
+            /// - `Back` goes back to the page the current one was reached from, see
+            ///   [`Self::go_back()`].
+            /// - `GoTo(page)` truncates the history back to `page` if it was visited, as if going
+            ///   back to it, and otherwise adds the current page to the history.
+            /// - `Quit` returns `ControlFlow::Break(())` without changing the page, leaving it to
+            ///   the caller to quit, e.g. using `relm4::main_application().quit()`.
+            pub fn navigate(
+                &mut self,
+                action: [<$Page NavAction>],
+            ) -> ::core::ops::ControlFlow<()> {
+                match action {
+                    [<$Page NavAction>]::Next => {
+                        if let Some(next) = self.next_page() {
+                            self.history.push(self.page);
+                            self.page = next;
+                        }
+                    }
+                    [<$Page NavAction>]::Back => {
+                        self.go_back();
+                    }
+                    [<$Page NavAction>]::GoTo(page) => {
+                        if let Some(i) = self.history.iter().position(|visited| *visited == page) {
+                            self.history.truncate(i);
+                        } else if page != self.page {
+                            self.history.push(self.page);
+                        }
+                        self.page = page;
+                    }
+                    [<$Page NavAction>]::Quit => return ::core::ops::ControlFlow::Break(()),
+                }
+                self.sync_stack();
+                ::core::ops::ControlFlow::Continue(())
+            }
+
+            /// A new `gtk::Stack` with the widget of each page, see [`Self::fill_stack()`].
+            pub fn stack(
+                &self,
+                transition: $crate::relm4::gtk::StackTransitionType,
+                duration: u32,
+            ) -> $crate::relm4::gtk::Stack {
+                let stack = $crate::relm4::gtk::Stack::builder()
+                    .transition_type(transition)
+                    .transition_duration(duration)
+                    .build();
+                self.fill_stack(&stack);
+                stack
+            }
+
+            /// Add the widget of each page to `stack`, named after the `name()` of the page, and
+            /// show the current page in it from now on.
+            ///
+            /// A widget only has one parent, so calling it again moves the pages out of the
+            /// previous stack into the new one, which is then kept in sync instead.
+            pub fn fill_stack(&self, stack: &$crate::relm4::gtk::Stack) {
+                use $crate::relm4::ComponentController as _;
+                let previous = self.stack.replace(Some(stack.clone()));
+                $(
+                    let widget = self.[<$page:snake _page>].widget();
+                    if let Some(previous) = &previous {
+                        previous.remove(widget);
+                    }
+                    stack.add_named(widget, Some($Page::[<$page:camel>].name()));
+                )+
+                self.sync_stack();
+            }
+
+            /// Show the current page in the stack last given to `fill_stack()`, if any.
+            fn sync_stack(&self) {
+                if let Some(stack) = &*self.stack.borrow() {
+                    stack.set_visible_child_name(self.page.name());
+                }
+            }
+
             fn _default(sender: ComponentSender<Self>) -> Self {Self {
                 page: $Page::default(),
+                history: Vec::new(),
+                stack: ::core::cell::RefCell::new(None),
                 $(
                     [<$page:snake _page>]: [<$page:camel Page>]::builder()
                         .launch(())
@@ -55,6 +420,11 @@ macro_rules! generate_pages {
             }}
         }
     }};
+    (@skip_if $model:ident) => { false };
+    (@skip_if $model:ident $skip:expr) => {{
+        let skip: fn(&Self) -> bool = $skip;
+        skip($model)
+    }};
     (@$page:ident $AppMsg:ident) => { $crate::paste::paste! {
         |msg| match msg {
             [<$page:camel PageOutput>]::Nav(action) => $AppMsg::Nav(action),
@@ -63,194 +433,549 @@ macro_rules! generate_pages {
     (@$page:ident $AppMsg:ident $forward:expr) => { $forward };
 }
 
-/// Generate a [`relm4::SimpleComponent`].
+/// Parts shared by the macros generating components.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __generate_component {
+    (@model $comp:ident [] $($model:tt)+) => { $crate::paste::paste! {
+        #[derive(Debug, Default)]
+        pub struct [<$comp>] {$($model)+}
+    }};
+    (@model $comp:ident []) => { $crate::paste::paste! {
+        #[derive(Debug, Default)]
+        pub struct [<$comp>];
+    }};
+    (@model $comp:ident [$modelexpr:expr] $($model:tt)+) => { $crate::paste::paste! {
+        #[derive(Debug)]
+        pub struct [<$comp>] {$($model)+}
+    }};
+    (@model $comp:ident [$modelexpr:expr]) => { $crate::paste::paste! {
+        #[derive(Debug)]
+        pub struct [<$comp>];
+    }};
+    (@model_expr) => { Self::default() };
+    (@model_expr $modelexpr:expr) => { $modelexpr };
+    (@out $comp:ident {$( $out:tt )*}) => { $crate::paste::paste! {
+        #[derive(Debug)]
+        pub enum [<$comp Output>] {
+            $($out)*
+        }
+    }};
+    (@out $comp:ident $outty:ty) => { };
+    (@outty $comp:ident {$( $out:tt )*}) => { $crate::paste::paste! { [<$comp Output>] }};
+    (@outty $comp:ident $outty:ty) => { $outty };
+    (@level) => { $crate::__private::TraceLevel::Trace };
+    (@level trace) => { $crate::__private::TraceLevel::Trace };
+    (@level debug) => { $crate::__private::TraceLevel::Debug };
+    (@level info) => { $crate::__private::TraceLevel::Info };
+    (@level warn) => { $crate::__private::TraceLevel::Warn };
+    (@level error) => { $crate::__private::TraceLevel::Error };
+    (@level off) => { $crate::__private::TraceLevel::Off };
+    (@variant $message:ident) => { ::core::option::Option::None };
+    (@variant $message:ident $($msg:ident)+) => {
+        ::core::option::Option::Some(match &$message {
+            $(Self::Input::$msg { .. } => ::core::stringify!($msg)),+
+        })
+    };
+    (@localref $initmodel:ident) => {};
+    (@localref $initmodel:ident $local_ref:ident {$($inner:tt)+} $($next:tt)*) => {
+        let $local_ref = {$($inner)+};
+        $crate::__generate_component!(@localref $initmodel $($next)*);
+    };
+    (@localref $initmodel:ident $local_ref:ident $nextident:ident $($next:tt)*) => {
+        let $local_ref = &$initmodel.$local_ref;
+        $crate::__generate_component!(@localref $initmodel $nextident $($next)*);
+    };
+    (@localref $initmodel:ident $local_ref:ident) => {
+        let $local_ref = &$initmodel.$local_ref;
+    };
+}
+
+/// Generate a [`relm4::factory::FactoryComponent`].
 ///
-/// This expands to
-/// - declaration of the model struct (`struct MyLabel { … }`)
-///   - this comes with `#[derive(Debug, Derive)]`
-/// - declaration of `enum MyLabelMsg { … }` for `type Input = MyLabelMsg;`
-/// - impl `fn init()` if the optional `init()` is omitted in macro invocation
-/// - `fn update()` alongside the declaration of the variants in `MyLabelMsg`
-/// - declaration of `enum MyLabelOutput { … }` for `type Output = MyLabelOutput` (unless another
-///   type is specified otherwise)
+/// ```text
+/// generate_factory_component!(MyRow { field: Type, … }:
+///   trace(level)                                      // optional
+///   type ParentWidget = gtk::Box;
+///   type Index = MyIndex;                             // optional, DynamicIndex by default
+///   type ParentInput = ParentMsg;                     // optional, see below
+///   init_model(value: InitType, index, sender) { … }  // optional
+///   update(self, message, sender) {
+///     Variant => expr,
+///     Variant(param: Type, …) => expr,
+///   } => { OutputVariant(Type), … }                   // or a single-token type
 ///
-/// # Examples
+///   gtk::Box { … }
+/// );
+/// ```
 ///
-/// Here is an over-engineered way to show a button labelled "Hello, World!". When the button is
-/// clicked, "Hello, World!" is printed in the console.
+/// The sections come in this order. `init_model()` evaluates to the model (i.e. `Self`); if it is
+/// omitted, the model is `Self::default()` and `type Init = ();`. Inside the view, the model is
+/// accessed using `self` instead of `model`. The span of `init_model()` is named `init_model`.
+///
+/// Like [`generate_async_component!`], this is a `macro_rules!` macro without the additions of
+/// [`kurage::generate_component!`]: the handlers are only `Variant` or
+/// `Variant(param: Type, …)`, without attributes or `emit`, the model has no generics, field
+/// defaults, `#[from_init]` or `#[sensitive]`, and `children`, `commands`, `reduce`,
+/// `subscriptions` and the hooks aren't available.
+///
+/// If `type ParentInput = …;` is specified, `impl From<MyRowOutput> for ParentInput` is generated,
+/// mapping each variant to the variant with the same name in the parent's input enum. This way,
+/// outputs can be forwarded using `.forward(sender.input_sender(), ParentInput::from)`. The
+/// variants in the `=> { … }` block then need named parameters like those in `update()`, e.g.
+/// `Remove(index: DynamicIndex)`.
+///
+/// # Examples
 ///
 /// ```
-/// use kurage_macro_rules::generate_component;
+/// use kurage::generate_component;
+/// use kurage_macro_rules::generate_factory_component;
 /// use relm4::prelude::*;
+/// use relm4::factory::{DynamicIndex, FactoryVecDeque};
 /// use relm4::gtk::{self, prelude::*};
-/// generate_component!(MyLabel:
-///   update(self, message, sender) {} => {}
-///   //                            ┯━    ┬─
-///   //          enum Self::Input ─╯     │
-///   //                                  │
-///   // enum Self::Output (you can also specify your own types)
-///
-///   gtk::Label {
-///     set_label: "Hello, World!",
+///
+/// generate_factory_component!(UserRow {
+///   name: String,
+/// }:
+///   type ParentWidget = gtk::Box;
+///   type ParentInput = UserListMsg;
+///
+///   init_model(name: String, index, sender) {
+///     Self { name }
+///   }
+///   update(self, message, sender) {
+///     Rename(name: String) => self.name = name,
+///   } => { Remove(index: DynamicIndex) }
+///
+///   gtk::Box {
+///     gtk::Label {
+///       #[watch]
+///       set_label: &self.name,
+///     },
+///     gtk::Button {
+///       set_label: "Remove",
+///       connect_clicked[sender, index] => move |_| {
+///         sender.output(UserRowOutput::Remove(index.clone())).unwrap();
+///       },
+///     },
 ///   },
 /// );
 ///
-/// #[derive(Debug)] // required for all fields of MyOtherComponent
-/// struct MyOtherComponentInner {
-///   mylabel: relm4::component::Connector<MyLabel>,
+/// #[derive(Debug)]
+/// struct UserListInner {
+///   users: FactoryVecDeque<UserRow>,
 /// }
-/// impl Default for MyOtherComponentInner {
+/// impl Default for UserListInner {
 ///   fn default() -> Self {
-///     Self { mylabel: MyLabel::builder().launch(()) }
+///     Self {
+///       users: FactoryVecDeque::builder()
+///         .launch(gtk::Box::default())
+///         .detach(),
+///     }
 ///   }
 /// }
 ///
-/// generate_component!(MyOtherComponent {
-///   btn: gtk::Button,
-///   inner: MyOtherComponentInner,
+/// generate_component!(UserList {
+///   inner: UserListInner,
 /// }:
-///   init[btn](root, sender, model, widgets) /* for my_init_var: MyInitType */ {
-///     //──┬──                               ══════════════════════════════╤══
-///     //  ╰─ optional, a space separated list of things for #[local_ref]  │
-///     //                                                                  ╵
-///     //     when you would like to set anything other than `type Init = ();`
-///     //
-///     // NOTE: init() is entirely optional, but update() is required.
-///     //
-///     // code in this block will be run only after `view_output!()`.
-///     // also, the model (i.e. `self`) is initialized using `Self::default()`.
-///
-///     model.inner.mylabel.detach_runtime(); // model is mutable
-///   }
 ///   update(self, message, sender) {
-///     ButtonClicked => println!("Hello, World!"),
+///     Remove(index: DynamicIndex) => {
+///       self.inner.users.guard().remove(index.current_index());
+///     },
 ///   } => {}
 ///
-///   gtk::Box {
-///     #[local_ref] btn ->
-///     gtk::Button {
-///       connect_clicked => Self::Input::ButtonClicked,
+///   gtk::Box {}
+/// );
+/// ```
+#[macro_export]
+macro_rules! generate_factory_component {
+    ($comp:ident $({$($model:tt)+})?:
+        $(trace($level:ident))?
+        type ParentWidget = $ParentWidget:ty;
+        $(type Index = $Index:ty;)?
+        $(type ParentInput = $ParentInput:path;)?
+        $(init_model($value:ident: $InitType:ty, $index:ident, $initsender:ident) $initblock:block)?
+        update($self:ident, $message:ident, $sender:ident) {
+            $( $msg:ident$(($($param:ident: $paramtype:ty),+$(,)?))? => $msghdl:expr ),*$(,)?
+        }
+        => $out:tt
+        $($viewtt:tt)*
+    ) => { $crate::paste::paste! {
+        $crate::__generate_component!{ @model $comp [] $($($model)+)?}
+        #[allow(dead_code)]
+        #[derive(Clone, Debug)]
+        pub enum [<$comp Msg>] {
+            $($msg$(($($paramtype),+))?),*
+        }
+
+        $crate::generate_factory_component!(@out $comp $out);
+        $($crate::generate_factory_component!(@forward $comp $ParentInput $out);)?
+
+        // HACK: similar to generate_component!(), this ensures `self`, `sender` and `index` in the
+        // view refer to the ones generated by relm4. The first occurrence of each ident must be
+        // the one written here, hence `init_model()` comes first and `update()` uses `self`.
+        #[::kurage::mangle_ident(self)]
+        #[::kurage::mangle_ident(sender)]
+        #[::kurage::mangle_ident(index)]
+        #[$crate::relm4::factory(pub)]
+        impl $crate::relm4::factory::FactoryComponent for $comp {
+            #[allow(unused_variables)]
+            fn init_model(
+                init: Self::Init,
+                index: &Self::Index,
+                sender: $crate::relm4::factory::FactorySender<Self>,
+            ) -> Self {
+                let _kurage_trace = $crate::__private::TraceGuard::new(
+                    $crate::__generate_component!(@level $($level)?),
+                    ::core::stringify!($comp),
+                    "init_model",
+                    ::core::option::Option::None,
+                );
+                $crate::generate_factory_component!(@init_model init index sender $($value $index $initsender $initblock)?)
+            }
+
+            fn update(&mut self, $message: Self::Input, $sender: $crate::relm4::factory::FactorySender<Self>) {
+                let _kurage_trace = $crate::__private::TraceGuard::new(
+                    $crate::__generate_component!(@level $($level)?),
+                    ::core::stringify!($comp),
+                    "update",
+                    $crate::__generate_component!(@variant $message $($msg)*),
+                );
+                match $message {
+                    $(Self::Input::$msg$(($($param),+))? => $msghdl),*
+                }
+            }
+
+            type ParentWidget = $ParentWidget;
+            $(type Index = $Index;)?
+            #[allow(unused_parens)]
+            type Init = ($($InitType)?);
+            type Input = [<$comp Msg>];
+            type Output = $crate::__generate_component!(@outty $comp $out);
+            type CommandOutput = ();
+
+            view! { $($viewtt)* }
+        }
+    }};
+    (@init_model $init:ident $index:ident $sender:ident) => { Self::default() };
+    (@init_model $init:ident $index:ident $sender:ident $value:ident $userindex:ident $initsender:ident $initblock:block) => {{
+        let $value = $init;
+        let $userindex = $index;
+        let $initsender = $sender;
+        $initblock
+    }};
+    (@out $comp:ident {$( $out:ident$(($($outparam:ident: $outty:ty),+$(,)?))? ),*$(,)?}) => {
+        $crate::__generate_component!(@out $comp { $($out$(($($outty),+))?),* });
+    };
+    (@out $comp:ident $out:tt) => {
+        $crate::__generate_component!(@out $comp $out);
+    };
+    (@forward $comp:ident $ParentInput:path {$( $out:ident$(($($outparam:ident: $outty:ty),+$(,)?))? ),*$(,)?}) => { $crate::paste::paste! {
+        impl From<[<$comp Output>]> for $ParentInput {
+            fn from(output: [<$comp Output>]) -> Self {
+                match output {
+                    $([<$comp Output>]::$out$(($($outparam),+))? => Self::$out$(($($outparam),+))?),*
+                }
+            }
+        }
+    }};
+}
+
+/// Generate a [`relm4::component::AsyncComponent`].
+///
+/// ```text
+/// generate_async_component!(MyPage { field: Type, … }:
+///   trace(level)                                   // optional
+///   model = expr;                                  // optional, Self::default() by default
+///   preinit { … }                                  // optional, requires init()
+///   init[local_ref …](root, sender, model, widgets) for name: InitType { … }
+///                                                  // optional, and so are [] and `for`
+///   loading_widgets(root) { … }                    // optional
+///   update(self, message, sender, root) {          // `root` is optional
+///     Variant => expr,
+///     Variant(param: Type, …) => expr,
+///   } => { OutputVariant(Type), … }                // or a single-token type
+///
+///   gtk::Box { … }
+/// );
+/// ```
+///
+/// The sections come in this order, where
+/// - the `init()` block and the message handlers in `update()` are `async`, so they may `.await`
+/// - `loading_widgets()` must evaluate to `Option<relm4::loading_widgets::LoadingWidgets>`, which
+///   are shown until `init()` finishes
+/// - `update()` accepts an optional fourth argument for `root: &Self::Root`
+///
+/// This is a `macro_rules!` macro, so it only accepts the syntax above, and mistakes are reported
+/// as "no rules expected …" errors. Unlike [`kurage::generate_component!`], it doesn't support
+/// struct variants, attributes on handlers, `emit`, generics, field defaults, `#[from_init]`,
+/// `#[sensitive]`, the attributes of the model and the `children`, `commands`, `reduce`,
+/// `subscriptions`, `shutdown` and `post_view` sections.
+///
+/// `type CommandOutput` is always `()`. With the `tracing` feature, the span of `init()` only
+/// covers the `init()` block, but the time logged includes building the view.
 ///
-///       set_child: Some(model.inner.mylabel.widget()),
+/// # Examples
+///
+/// ```
+/// use kurage_macro_rules::generate_async_component;
+/// use relm4::prelude::*;
+/// use relm4::gtk::{self, prelude::*};
+/// use relm4::loading_widgets::LoadingWidgets;
+///
+/// async fn probe_disks() -> Vec<String> {
+///   vec!["/dev/sda".into()]
+/// }
+///
+/// generate_async_component!(DiskPage {
+///   disks: Vec<String>,
+/// }:
+///   init(root, sender, model, widgets) {
+///     // this block is run after `view_output!()`
+///     model.disks = probe_disks().await;
+///   }
+///   loading_widgets(root) {
+///     relm4::view! {
+///       #[local]
+///       root {
+///         #[name(spinner)]
+///         gtk::Spinner { start: () }
+///       }
 ///     }
+///     Some(LoadingWidgets::new(root, spinner))
 ///   }
+///   update(self, message, sender) {
+///     Refresh => self.disks = probe_disks().await,
+///   } => {}
+///
+///   gtk::Box {
+///     gtk::Button {
+///       set_label: "Refresh",
+///       connect_clicked => Self::Input::Refresh,
+///     },
+///   },
 /// );
 /// ```
 #[macro_export]
-macro_rules! generate_component {
+macro_rules! generate_async_component {
     ($comp:ident $({$($model:tt)+})?:
+        $(trace($level:ident))?
+        $(model = $modelexpr:expr;)?
         $(
-        $(preinit /* $([$($mangle:ident)+])? */ { $($preinit:tt)+ })?
+        $(preinit { $($preinit:tt)+ })?
         init$([$($local_ref:tt)+])?($root:ident, $initsender:ident, $initmodel:ident, $initwidgets:ident) $(for $init:ident: $InitType:ty)? $initblock:block
         )?
-        update($self:ident, $message:ident, $sender:ident) {
+        $(loading_widgets($lwroot:ident) $lwblock:block)?
+        update($self:ident, $message:ident, $sender:ident $(, $updroot:ident)?) {
             $( $msg:ident$(($($param:ident: $paramtype:ty),+$(,)?))? => $msghdl:expr ),*$(,)?
         }
         => $out:tt
         $($viewtt:tt)*
     ) => { $crate::paste::paste! {
-        $crate::generate_component!{ @model $comp $($($model)+)?}
+        $crate::__generate_component!{ @model $comp [$($modelexpr)?] $($($model)+)?}
         #[allow(dead_code)]
         #[derive(Clone, Debug)]
         pub enum [<$comp Msg>] {
             $($msg$(($($paramtype),+))?),*
         }
 
-        $crate::generate_component!(@out $comp $out);
+        $crate::__generate_component!(@out $comp $out);
 
-        // HACK: this ensures `#[watch]` is parsed correctly for `model` idents
+        // HACK: see kurage::generate_component!()
         #[::kurage::mangle_ident(model)]
         $(#[::kurage::mangle_ident($initmodel)])?
-        // #[::kurage::mangle_ident(root)]
-        // $(#[::kurage::mangle_ident($root)])?
-        // #[::kurage::mangle_ident(init)]
-        // $($(#[::kurage::mangle_ident($init)])?)?
-        // $($($($(#[::kurage::mangle_ident($mangle)])+)?)?)?
-        #[$crate::relm4::component(pub)]
-        impl $crate::relm4::SimpleComponent for $comp {
+        #[$crate::relm4::component(pub async)]
+        impl $crate::relm4::component::AsyncComponent for $comp {
             #[allow(unused_parens)]
             type Init = ($($($InitType)?)?);
             type Input = [<$comp Msg>];
-            type Output = $crate::generate_component!(@outty $comp $out);
+            type Output = $crate::__generate_component!(@outty $comp $out);
+            type CommandOutput = ();
+
+            $(
+            fn init_loading_widgets(
+                $lwroot: Self::Root,
+            ) -> Option<$crate::relm4::loading_widgets::LoadingWidgets> $lwblock
+            )?
 
             #[allow(clippy::used_underscore_binding)]
             #[allow(unused_variables)]
-            fn init(
+            async fn init(
                 init: Self::Init,
                 root: Self::Root,
-                $sender: $crate::relm4::ComponentSender<Self>,
-            ) -> $crate::relm4::ComponentParts<Self> {
+                $sender: $crate::relm4::component::AsyncComponentSender<Self>,
+            ) -> $crate::relm4::component::AsyncComponentParts<Self> {
+                let kurage_trace = $crate::__private::TraceSpan::new(
+                    $crate::__generate_component!(@level $($level)?),
+                    ::core::stringify!($comp),
+                    "init",
+                    ::core::option::Option::None,
+                );
+                $($(let $init = init;)?)?
                 #[allow(unused_mut)]
-                let mut model = Self::default();
+                let mut model = $crate::__generate_component!(@model_expr $($modelexpr)?);
                 $(
                     #[allow(unused_mut, unused_assignments)]
                     let mut $initmodel = model;
 
-                    $($crate::generate_component!(@localref $initmodel $($local_ref)+))?;
+                    $($crate::__generate_component!(@localref $initmodel $($local_ref)+))?;
 
                     let $root = root.clone();
-                    $(let $init = init;)?
                     $($($preinit)+)?
                 )?
 
-                // HACK: invoking view_output!() directly gives `()` when $init* is given.
-                // I don't know why this fixes the issue. — mado
                 let widgets = [<view _output>]!();
 
                 $(
-                // HACK: this solves variable name obfuscation in macro_rules! {}
                 let $initwidgets = widgets;
                 #[allow(unused_variables)]
                 let $initsender = $sender;
 
-                $initblock
+                kurage_trace.instrument(async $initblock).await;
 
                 let model = $initmodel;
                 let widgets = $initwidgets;
                 )?
 
-                $crate::relm4::ComponentParts { model, widgets }
+                $crate::relm4::component::AsyncComponentParts { model, widgets }
             }
 
-            fn update(&mut $self, $message: Self::Input, $sender: $crate::relm4::ComponentSender<Self>) {
-                // tracing::trace!(?$message, "{}", concat!(stringify!($comp), ": received message"));
-                match $message {
-                    $(Self::Input::$msg$(($($param),+))? => $msghdl),*
-                }
+            #[allow(unused_variables)]
+            async fn update(
+                &mut $self,
+                $message: Self::Input,
+                $sender: $crate::relm4::component::AsyncComponentSender<Self>,
+                root: &Self::Root,
+            ) {
+                $(let $updroot = root;)?
+                let kurage_trace = $crate::__private::TraceSpan::new(
+                    $crate::__generate_component!(@level $($level)?),
+                    ::core::stringify!($comp),
+                    "update",
+                    $crate::__generate_component!(@variant $message $($msg)*),
+                );
+                kurage_trace.instrument(async {
+                    match $message {
+                        $(Self::Input::$msg$(($($param),+))? => $msghdl),*
+                    }
+                }).await;
             }
 
             view! { $($viewtt)* }
         }
     }};
-    (@model $comp:ident $($model:tt)+) => { $crate::paste::paste! {
-        #[derive(Debug, Default)]
-        pub struct [<$comp>] {$($model)+}
-    }};
-    (@model $comp:ident) => { $crate::paste::paste! {
-        #[derive(Debug, Default)]
-        pub struct [<$comp>];
-    }};
-    (@out $comp:ident {$( $out:tt )*}) => { $crate::paste::paste! {
-        #[derive(Debug)]
-        pub enum [<$comp Output>] {
-            $($out)*
+}
+
+/// Generate a [`relm4::Worker`].
+///
+/// ```text
+/// generate_worker!(MyWorker { field: Type, … }:
+///   trace(level)                                  // optional
+///   model = expr;                                 // optional, Self::default() by default
+///   init(sender, model) for name: InitType { … }  // optional, and so is `for`
+///   update(self, message, sender) {
+///     Variant => expr,
+///     Variant(param: Type, …) => expr,
+///   } => { OutputVariant(Type), … }               // or a single-token type
+/// );
+/// ```
+///
+/// There is no view, and `init()` and `update()` are traced at the level given by `trace(level)`.
+/// The worker is launched using [`relm4::ComponentBuilder::detach_worker`].
+///
+/// Only the syntax above is accepted, as in [`generate_async_component!`]: none of the additions
+/// of [`kurage::generate_component!`] such as `emit`, struct variants or `#[sensitive]` are
+/// available.
+///
+/// # Examples
+///
+/// ```
+/// use kurage_macro_rules::generate_worker;
+/// use relm4::prelude::*;
+///
+/// generate_worker!(Copier {
+///   copied: usize,
+/// }:
+///   init(sender, model) for total: usize {
+///     // optional, just like `init()` in `generate_component!()`
+///     sender.output(CopierOutput::Progress(0, total)).unwrap();
+///   }
+///   update(self, message, sender) {
+///     Copy(files: Vec<String>) => {
+///       // this runs in a separate thread, so blocking is fine
+///       self.copied += files.len();
+///       sender.output(CopierOutput::Done(self.copied)).unwrap();
+///     },
+///   } => { Progress(usize, usize), Done(usize) }
+/// );
+///
+/// # fn launch() {
+/// let worker = Copier::builder().detach_worker(42).detach();
+/// worker.emit(CopierMsg::Copy(vec!["/etc/fstab".into()]));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! generate_worker {
+    ($comp:ident $({$($model:tt)+})?:
+        $(trace($level:ident))?
+        $(model = $modelexpr:expr;)?
+        $(init($initsender:ident, $initmodel:ident) $(for $init:ident: $InitType:ty)? $initblock:block)?
+        update($self:ident, $message:ident, $sender:ident) {
+            $( $msg:ident$(($($param:ident: $paramtype:ty),+$(,)?))? => $msghdl:expr ),*$(,)?
+        }
+        => $out:tt
+    ) => { $crate::paste::paste! {
+        $crate::__generate_component!{ @model $comp [$($modelexpr)?] $($($model)+)?}
+        #[allow(dead_code)]
+        #[derive(Clone, Debug)]
+        pub enum [<$comp Msg>] {
+            $($msg$(($($paramtype),+))?),*
+        }
+
+        $crate::__generate_component!(@out $comp $out);
+
+        impl $crate::relm4::Worker for $comp {
+            #[allow(unused_parens)]
+            type Init = ($($($InitType)?)?);
+            type Input = [<$comp Msg>];
+            type Output = $crate::__generate_component!(@outty $comp $out);
+
+            #[allow(unused_variables)]
+            fn init(init: Self::Init, $sender: $crate::relm4::ComponentSender<Self>) -> Self {
+                let _kurage_trace = $crate::__private::TraceGuard::new(
+                    $crate::__generate_component!(@level $($level)?),
+                    ::core::stringify!($comp),
+                    "init",
+                    ::core::option::Option::None,
+                );
+                $($(let $init = init;)?)?
+                #[allow(unused_mut)]
+                let mut model = $crate::__generate_component!(@model_expr $($modelexpr)?);
+                $(
+                    #[allow(unused_mut)]
+                    let mut $initmodel = model;
+                    let $initsender = $sender;
+
+                    $initblock
+
+                    let model = $initmodel;
+                )?
+                model
+            }
+
+            fn update(&mut $self, $message: Self::Input, $sender: $crate::relm4::ComponentSender<Self>) {
+                let _kurage_trace = $crate::__private::TraceGuard::new(
+                    $crate::__generate_component!(@level $($level)?),
+                    ::core::stringify!($comp),
+                    "update",
+                    $crate::__generate_component!(@variant $message $($msg)*),
+                );
+                match $message {
+                    $(Self::Input::$msg$(($($param),+))? => $msghdl),*
+                }
+            }
         }
     }};
-    (@out $comp:ident $outty:ty) => { };
-    (@outty $comp:ident {$( $out:tt )*}) => { $crate::paste::paste! { [<$comp Output>] }};
-    (@outty $comp:ident $outty:ty) => { $outty };
-    (@localref $initmodel:ident) => {};
-    (@localref $initmodel:ident $local_ref:ident {$($inner:tt)+} $($next:tt)*) => {
-        let $local_ref = {$($inner)+};
-        $crate::generate_component!(@localref $initmodel $($next)*);
-    };
-    (@localref $initmodel:ident $local_ref:ident $nextident:ident $($next:tt)*) => {
-        let $local_ref = &$initmodel.$local_ref;
-        $crate::generate_component!(@localref $initmodel $nextident $($next)*);
-    };
-    (@localref $initmodel:ident $local_ref:ident) => {
-        let $local_ref = &$initmodel.$local_ref;
-    };
 }
 
 /// Macros used by other 🪼 macros.
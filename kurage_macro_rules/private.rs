@@ -0,0 +1,147 @@
+//! Items used by code generated by 🪼 macros. Not public API.
+
+/// The level of the spans of a generated component, chosen using `trace(level)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TraceLevel {
+    #[default]
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    /// Don't create spans at all.
+    Off,
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! at_level {
+    ($level:expr, $mac:ident!($($args:tt)*)) => {
+        match $level {
+            TraceLevel::Trace => Some(tracing::$mac!(tracing::Level::TRACE, $($args)*)),
+            TraceLevel::Debug => Some(tracing::$mac!(tracing::Level::DEBUG, $($args)*)),
+            TraceLevel::Info => Some(tracing::$mac!(tracing::Level::INFO, $($args)*)),
+            TraceLevel::Warn => Some(tracing::$mac!(tracing::Level::WARN, $($args)*)),
+            TraceLevel::Error => Some(tracing::$mac!(tracing::Level::ERROR, $($args)*)),
+            TraceLevel::Off => None,
+        }
+    };
+}
+
+#[cfg(feature = "tracing")]
+fn span(
+    level: TraceLevel,
+    component: &'static str,
+    handler: &'static str,
+    message: Option<&'static str>,
+) -> Option<tracing::Span> {
+    at_level!(
+        level,
+        span!("component", name = component, handler, message)
+    )
+}
+
+#[cfg(feature = "tracing")]
+fn finished(level: TraceLevel, start: std::time::Instant) {
+    at_level!(level, event!(elapsed = ?start.elapsed(), "finished"));
+}
+
+/// Enters a span for `init()`/`update()` of a generated component until dropped, at which point
+/// the time spent is logged.
+#[must_use]
+pub struct TraceGuard {
+    #[cfg(feature = "tracing")]
+    inner: Option<(tracing::span::EnteredSpan, std::time::Instant)>,
+    #[cfg(feature = "tracing")]
+    level: TraceLevel,
+}
+
+impl TraceGuard {
+    #[allow(unused_variables, clippy::missing_const_for_fn)]
+    pub fn new(
+        level: TraceLevel,
+        component: &'static str,
+        handler: &'static str,
+        message: Option<&'static str>,
+    ) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            inner: span(level, component, handler, message)
+                .map(|span| (span.entered(), std::time::Instant::now())),
+            #[cfg(feature = "tracing")]
+            level,
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        if let Some((_, start)) = &self.inner {
+            finished(self.level, *start);
+        }
+    }
+}
+
+/// Same as [`TraceGuard`] but for `async` handlers, where the span is only entered while polling
+/// the futures passed to [`TraceSpan::instrument`].
+#[must_use]
+pub struct TraceSpan {
+    #[cfg(feature = "tracing")]
+    inner: Option<(tracing::Span, std::time::Instant)>,
+    #[cfg(feature = "tracing")]
+    level: TraceLevel,
+}
+
+impl TraceSpan {
+    #[allow(unused_variables, clippy::missing_const_for_fn)]
+    pub fn new(
+        level: TraceLevel,
+        component: &'static str,
+        handler: &'static str,
+        message: Option<&'static str>,
+    ) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            inner: span(level, component, handler, message)
+                .map(|span| (span, std::time::Instant::now())),
+            #[cfg(feature = "tracing")]
+            level,
+        }
+    }
+
+    pub async fn instrument<F: std::future::Future>(&self, fut: F) -> F::Output {
+        #[cfg(feature = "tracing")]
+        if let Some((span, _)) = &self.inner {
+            return tracing::Instrument::instrument(fut, span.clone()).await;
+        }
+        fut.await
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for TraceSpan {
+    fn drop(&mut self) {
+        if let Some((span, start)) = &self.inner {
+            span.in_scope(|| finished(self.level, *start));
+        }
+    }
+}
+
+/// Logs a warning if an output sent by `emit` in a generated component couldn't be delivered
+/// because the receiver is gone.
+#[allow(unused_variables, clippy::needless_pass_by_value)]
+pub fn emitted<T>(result: Result<(), T>, component: &'static str) {
+    #[cfg(feature = "tracing")]
+    if result.is_err() {
+        tracing::warn!(component, "output dropped, the receiver is gone");
+    }
+}
+
+/// Printed instead of `#[sensitive]` fields in generated `Debug` impls.
+pub struct Redacted;
+
+impl std::fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
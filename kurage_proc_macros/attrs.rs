@@ -0,0 +1,92 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse::ParseStream, punctuated::Punctuated, Token};
+
+/// Default derives removed using `#[no_derive]` or `#[no_derive(Trait, …)]`.
+#[derive(Default)]
+enum NoDerive {
+    #[default]
+    Nothing,
+    All,
+    Some(Vec<syn::Ident>),
+}
+
+/// Leading attributes and visibility of a generated item, e.g. `#[derive(Hash)] pub(crate)`.
+pub struct ItemAttrs {
+    pub attrs: Vec<syn::Attribute>,
+    pub vis: Option<syn::Visibility>,
+    no_derive: NoDerive,
+}
+
+impl syn::parse::Parse for ItemAttrs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attrs = input.call(syn::Attribute::parse_outer)?;
+        let mut no_derive = NoDerive::Nothing;
+        let mut res = Ok(());
+        attrs.retain(|attr| {
+            if !attr.path().is_ident("no_derive") {
+                return true;
+            }
+            let parsed = match &attr.meta {
+                syn::Meta::Path(_) => Ok(NoDerive::All),
+                syn::Meta::List(l) => l
+                    .parse_args_with(Punctuated::<syn::Ident, Token![,]>::parse_terminated)
+                    .map(|traits| NoDerive::Some(traits.into_iter().collect())),
+                syn::Meta::NameValue(nv) => Err(syn::Error::new_spanned(
+                    nv,
+                    "kurage: expected `#[no_derive]` or `#[no_derive(Trait, …)]`",
+                )),
+            };
+            match (parsed, &mut no_derive) {
+                (Err(e), _) => res = Err(e),
+                (Ok(NoDerive::Some(more)), NoDerive::Some(traits)) => traits.extend(more),
+                (Ok(parsed), NoDerive::Nothing | NoDerive::Some(_)) => no_derive = parsed,
+                (Ok(_), NoDerive::All) => {}
+            }
+            false
+        });
+        res?;
+        let vis = match input.parse()? {
+            syn::Visibility::Inherited => None,
+            vis => Some(vis),
+        };
+        Ok(Self {
+            attrs,
+            vis,
+            no_derive,
+        })
+    }
+}
+
+impl ItemAttrs {
+    pub const fn is_empty(&self) -> bool {
+        self.attrs.is_empty() && self.vis.is_none() && matches!(self.no_derive, NoDerive::Nothing)
+    }
+
+    /// Whether the default derive of `name` is kept.
+    pub fn derives(&self, name: &str) -> bool {
+        match &self.no_derive {
+            NoDerive::Nothing => true,
+            NoDerive::All => false,
+            NoDerive::Some(traits) => !traits.iter().any(|t| t == name),
+        }
+    }
+
+    /// The attributes, `#[derive]` with those in `defaults` that aren't opted out of, and the
+    /// visibility (`default_vis` if not specified).
+    pub fn to_tokens(&self, defaults: &[&str], default_vis: &TokenStream) -> TokenStream {
+        let attrs = &self.attrs;
+        let derives = (defaults.iter())
+            .filter(|d| self.derives(d))
+            .map(|d| format_ident!("{d}"));
+        let vis = self
+            .vis
+            .as_ref()
+            .map_or_else(|| default_vis.clone(), |vis| quote! { #vis });
+        quote! {
+            #(#attrs)*
+            #[derive(#(#derives),*)]
+            #vis
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::subscriptions::parse_duration;
+
+/// The hidden field of the model holding the messages held back.
+pub const FIELD: &str = "__kurage_coalesce";
+/// The hidden variant of the Msg enum releasing the message held back in a slot.
+pub const RELEASE: &str = "__KurageRelease";
+
+/// `#[debounce(300ms)]` or `#[throttle(100ms)]` on a handler of `update()`
+pub struct Coalesce {
+    /// `debounce` or `throttle`
+    kind: syn::Ident,
+    interval: TokenStream,
+}
+
+/// Remove `#[debounce(…)]` and `#[throttle(…)]` from `attrs`, returning the one given if any.
+pub fn take(attrs: &mut Vec<syn::Attribute>) -> syn::Result<Option<Coalesce>> {
+    let mut coalesce = None;
+    let mut res = Ok(());
+    attrs.retain(|attr| {
+        let Some(kind) =
+            (attr.path().get_ident()).filter(|ident| *ident == "debounce" || *ident == "throttle")
+        else {
+            return true;
+        };
+        let parsed = if coalesce.is_some() {
+            Err(syn::Error::new(
+                kind.span(),
+                "kurage: only one of #[debounce] and #[throttle] may be given",
+            ))
+        } else {
+            attr.parse_args_with(parse_duration)
+        };
+        match parsed {
+            Ok(interval) => {
+                coalesce = Some(Coalesce {
+                    kind: kind.clone(),
+                    interval,
+                });
+            }
+            Err(e) => res = Err(e),
+        }
+        false
+    });
+    res.map(|()| coalesce)
+}
+
+/// The hidden handler of the message releasing the message held back in a slot.
+pub fn release_handler() -> TokenStream {
+    let release = syn::Ident::new(RELEASE, Span::call_site());
+    quote! {
+        #[doc(hidden)]
+        #release(slot: usize) => { _ = slot; }
+    }
+}
+
+/// `let Some(message) = … else { return; };` holding back the messages of the variants of `msg`
+/// given with their `#[cfg]` attributes and their [`Coalesce`], and taking back the released ones.
+pub fn hold_back<'a, I: Iterator<Item = (TokenStream, &'a syn::Ident, &'a Coalesce)>>(
+    coalesced: I,
+    msg: &syn::Ident,
+    (selff, message, sender): (&syn::Ident, &syn::Ident, &syn::Ident),
+) -> TokenStream {
+    let field = syn::Ident::new(FIELD, Span::call_site());
+    let release = syn::Ident::new(RELEASE, Span::call_site());
+    let arms = coalesced
+        .enumerate()
+        .map(|(slot, (cfgs, variant, coalesce))| {
+            let Coalesce { kind, interval } = coalesce;
+            quote! {
+                #cfgs
+                #message @ #msg::#variant { .. } => #selff.#field.#kind(
+                    #slot,
+                    #message,
+                    #interval,
+                    #sender.input_sender(),
+                    #msg::#release,
+                ),
+            }
+        });
+    quote! {
+        let ::core::option::Option::Some(#message) = (match #message {
+            #(#arms)*
+            #msg::#release(slot) => #selff.#field.release(slot),
+            #message => ::core::option::Option::Some(#message),
+        }) else {
+            return;
+        };
+    }
+}
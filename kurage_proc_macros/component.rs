@@ -0,0 +1,484 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{parse::Parse, spanned::Spanned, Token};
+
+use crate::trace;
+
+/// The Output of the component.
+pub enum OutputArg {
+    /// `output = { Variant1, Variant2(Type) }` or no `output` at all.
+    Enum(TokenStream),
+    /// `output = Type`
+    Type(Box<syn::Type>),
+}
+
+/// The arguments of `#[kurage::component(…)]`.
+pub struct ComponentArgs {
+    output: OutputArg,
+    /// `trace = level`
+    trace: Option<trace::Level>,
+}
+
+impl Parse for ComponentArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut output = None;
+        let mut trace = None;
+        while !input.is_empty() {
+            let key = input.parse::<syn::Ident>()?;
+            let duplicate = match key.to_string().as_str() {
+                "output" => output.is_some(),
+                "trace" => trace.is_some(),
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "kurage: expected `output = { … }`, `output = Type` or `trace = level`",
+                    ))
+                }
+            };
+            if duplicate {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("kurage: duplicate argument `{key}`"),
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            if key == "trace" {
+                trace = Some(input.parse()?);
+            } else if input.peek(syn::token::Brace) {
+                let content;
+                syn::braced!(content in input);
+                output = Some(OutputArg::Enum(content.parse()?));
+            } else {
+                output = Some(OutputArg::Type(Box::new(input.parse()?)));
+            }
+            if input.parse::<Option<Token![,]>>()?.is_none() {
+                break;
+            }
+        }
+        if !input.is_empty() {
+            return Err(input.error("kurage: expected `,`"));
+        }
+        Ok(Self {
+            output: output.unwrap_or_else(|| OutputArg::Enum(TokenStream::new())),
+            trace,
+        })
+    }
+}
+
+/// A `#[msg]` handler that becomes a variant of the input enum.
+struct MsgHandler {
+    variant: syn::Ident,
+    func: syn::Ident,
+    docs: Vec<syn::Attribute>,
+    /// `None` for the sender, otherwise the binding and type of a field of the variant, and
+    /// whether it is `#[sensitive]`.
+    args: Vec<Option<(syn::Ident, syn::Type, bool)>>,
+}
+
+/// Convert a `snake_case` ident to `CamelCase`, keeping the span.
+fn snake_to_camel(ident: &syn::Ident) -> syn::Ident {
+    let camel: String = ident
+        .to_string()
+        .split('_')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let mut chars = s.chars();
+            chars.next().map_or_else(String::new, |c| {
+                c.to_uppercase().chain(chars).collect::<String>()
+            })
+        })
+        .collect();
+    syn::Ident::new(&camel, ident.span())
+}
+
+fn is_component_sender(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(p) => p
+            .path
+            .segments
+            .last()
+            .is_some_and(|seg| seg.ident == "ComponentSender"),
+        _ => false,
+    }
+}
+
+impl MsgHandler {
+    /// Parse the handler, removing `#[sensitive]` from its parameters.
+    fn new(attr: &syn::Attribute, f: &mut syn::ImplItemFn) -> syn::Result<Self> {
+        let variant = match &attr.meta {
+            syn::Meta::Path(_) => snake_to_camel(&f.sig.ident),
+            syn::Meta::List(l) => l.parse_args()?,
+            syn::Meta::NameValue(nv) => {
+                return Err(syn::Error::new(
+                    nv.span(),
+                    "kurage: expected `#[msg]` or `#[msg(VariantName)]`",
+                ))
+            }
+        };
+        let mut inputs = f.sig.inputs.iter_mut();
+        if !matches!(inputs.next(), Some(syn::FnArg::Receiver(_))) {
+            return Err(syn::Error::new(
+                f.sig.span(),
+                "kurage: #[msg] handlers must take `&mut self` or `&self`",
+            ));
+        }
+        let args = inputs
+            .map(|arg| {
+                let syn::FnArg::Typed(pt) = arg else {
+                    unreachable!("receiver can only be the first argument")
+                };
+                let len = pt.attrs.len();
+                pt.attrs.retain(|attr| !attr.path().is_ident("sensitive"));
+                let sensitive = pt.attrs.len() != len;
+                if is_component_sender(&pt.ty) {
+                    return Ok(None);
+                }
+                match &*pt.pat {
+                    syn::Pat::Ident(i) => Ok(Some((i.ident.clone(), (*pt.ty).clone(), sensitive))),
+                    pat => Err(syn::Error::new(
+                        pat.span(),
+                        "kurage: parameters of #[msg] handlers must be plain identifiers",
+                    )),
+                }
+            })
+            .collect::<syn::Result<_>>()?;
+        Ok(Self {
+            variant,
+            func: f.sig.ident.clone(),
+            docs: (f.attrs.iter())
+                .filter(|attr| attr.path().is_ident("doc"))
+                .cloned()
+                .collect(),
+            args,
+        })
+    }
+
+    fn variant_decl(&self) -> TokenStream {
+        let Self {
+            variant,
+            docs,
+            args,
+            ..
+        } = self;
+        let types = args.iter().flatten().map(|(_, ty, _)| ty);
+        if args.iter().flatten().next().is_none() {
+            quote! { #(#docs)* #variant }
+        } else {
+            quote! { #(#docs)* #variant(#(#types),*) }
+        }
+    }
+
+    fn match_arm(&self, sender: &syn::Ident) -> TokenStream {
+        let Self {
+            variant,
+            func,
+            args,
+            ..
+        } = self;
+        let fields = args.iter().flatten().map(|(name, _, _)| name);
+        let pat = if args.iter().flatten().next().is_none() {
+            quote! { Self::Input::#variant }
+        } else {
+            quote! { Self::Input::#variant(#(#fields),*) }
+        };
+        let call_args = args.iter().map(|arg| {
+            arg.as_ref().map_or_else(
+                || quote! { #sender.clone() },
+                |(name, _, _)| quote! { #name },
+            )
+        });
+        quote_spanned! { func.span()=> #pat => self.#func(#(#call_args),*), }
+    }
+
+    fn is_sensitive(&self) -> bool {
+        self.args
+            .iter()
+            .flatten()
+            .any(|(_, _, sensitive)| *sensitive)
+    }
+
+    /// The arm of `match self` in a `Debug` impl, printing `<redacted>` for sensitive fields.
+    fn debug_arm(&self) -> TokenStream {
+        let variant = &self.variant;
+        if self.args.iter().flatten().next().is_none() {
+            return quote! { Self::#variant => f.write_str(::core::stringify!(#variant)), };
+        }
+        let names = self.args.iter().flatten().map(|(name, _, _)| name);
+        let fields = self.args.iter().flatten().map(|(name, _, sensitive)| {
+            if *sensitive {
+                quote! { &::kurage::__private::Redacted }
+            } else {
+                quote! { #name }
+            }
+        });
+        quote! {
+            Self::#variant(#(#names),*) => f
+                .debug_tuple(::core::stringify!(#variant))
+                #(.field(#fields))*
+                .finish(),
+        }
+    }
+}
+
+/// Items in the `impl` block, sorted by where they end up.
+#[derive(Default)]
+struct ImplItems {
+    handlers: Vec<MsgHandler>,
+    /// `#[msg]` handlers, kept in an inherent impl.
+    handler_fns: Vec<syn::ImplItemFn>,
+    /// Everything else, moved into the `SimpleComponent` impl.
+    trait_items: Vec<syn::ImplItem>,
+    has_init: bool,
+    has_init_ty: bool,
+}
+
+impl ImplItems {
+    fn new(items: Vec<syn::ImplItem>) -> syn::Result<Self> {
+        let mut out = Self::default();
+        for impl_item in items {
+            out.push(impl_item)?;
+        }
+        Ok(out)
+    }
+
+    fn push(&mut self, impl_item: syn::ImplItem) -> syn::Result<()> {
+        match impl_item {
+            syn::ImplItem::Fn(mut f) => {
+                if let Some(pos) = f.attrs.iter().position(|attr| attr.path().is_ident("msg")) {
+                    let attr = f.attrs.remove(pos);
+                    self.handlers.push(MsgHandler::new(&attr, &mut f)?);
+                    self.handler_fns.push(f);
+                    return Ok(());
+                }
+                match f.sig.ident.to_string().as_str() {
+                    "init" => self.has_init = true,
+                    "update" => {
+                        return Err(syn::Error::new(
+                            f.sig.ident.span(),
+                            "kurage: update() is generated from #[msg] handlers",
+                        ))
+                    }
+                    _ => {}
+                }
+                self.trait_items.push(syn::ImplItem::Fn(f));
+            }
+            syn::ImplItem::Type(ty) => {
+                match ty.ident.to_string().as_str() {
+                    "Init" => self.has_init_ty = true,
+                    "Input" => {
+                        return Err(syn::Error::new(
+                            ty.ident.span(),
+                            "kurage: `type Input` is generated from #[msg] handlers",
+                        ))
+                    }
+                    _ => {}
+                }
+                self.trait_items.push(syn::ImplItem::Type(ty));
+            }
+            other => self.trait_items.push(other),
+        }
+        Ok(())
+    }
+}
+
+/// The input enum, with a `Debug` impl if some fields are `#[sensitive]`.
+fn msg_enum_decl(msg_enum: &syn::Ident, handlers: &[MsgHandler]) -> TokenStream {
+    let (msg_derive, msg_debug) = if handlers.iter().any(MsgHandler::is_sensitive) {
+        let arms = handlers.iter().map(MsgHandler::debug_arm);
+        (
+            quote! { #[derive(Clone)] },
+            Some(quote! {
+                impl ::core::fmt::Debug for #msg_enum {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        match self {
+                            #(#arms)*
+                        }
+                    }
+                }
+            }),
+        )
+    } else {
+        (quote! { #[derive(Clone, Debug)] }, None)
+    };
+
+    let variants = handlers.iter().map(MsgHandler::variant_decl);
+    quote! {
+        #[allow(dead_code)]
+        #msg_derive
+        pub enum #msg_enum {
+            #(#variants),*
+        }
+        #msg_debug
+    }
+}
+
+/// Enter the span of `init()` at the start of the `fn init()` written by the user.
+fn trace_written_init(
+    trait_items: &mut [syn::ImplItem],
+    init_trace: &TokenStream,
+) -> syn::Result<()> {
+    let guard: syn::Stmt = syn::parse2(init_trace.clone())?;
+    for impl_item in trait_items {
+        if let syn::ImplItem::Fn(f) = impl_item {
+            if f.sig.ident == "init" {
+                f.block.stmts.insert(0, guard.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn component(args: ComponentArgs, mut item: syn::ItemImpl) -> syn::Result<TokenStream> {
+    if let Some((_, path, _)) = &item.trait_ {
+        return Err(syn::Error::new(
+            path.span(),
+            "kurage: #[kurage::component] should be used on `impl MyComponent { … }`",
+        ));
+    }
+    let comp = match &*item.self_ty {
+        syn::Type::Path(p) => p.path.segments.last().map(|seg| seg.ident.clone()),
+        _ => None,
+    }
+    .ok_or_else(|| syn::Error::new(item.self_ty.span(), "kurage: expected a struct name"))?;
+    let msg_enum = format_ident!("{comp}Msg");
+    let out_enum = format_ident!("{comp}Output");
+    let ImplItems {
+        handlers,
+        handler_fns,
+        mut trait_items,
+        has_init,
+        has_init_ty,
+    } = ImplItems::new(std::mem::take(&mut item.items))?;
+
+    let (out_decl, out_ty) = match args.output {
+        OutputArg::Enum(variants) => (
+            Some(quote! {
+                #[derive(Debug)]
+                pub enum #out_enum {
+                    #variants
+                }
+            }),
+            quote! { #out_enum },
+        ),
+        OutputArg::Type(ty) => (None, quote! { #ty }),
+    };
+    let init_trace = trace::guard(
+        args.trace.as_ref(),
+        &comp,
+        "init",
+        &quote! { ::core::option::Option::None },
+    );
+    let message = format_ident!("message");
+    let update_trace = trace::guard(
+        args.trace.as_ref(),
+        &comp,
+        "update",
+        &trace::variant_name(
+            &message,
+            &quote! { Self::Input },
+            handlers.iter().map(|h| (TokenStream::new(), &h.variant)),
+            None,
+        ),
+    );
+    if has_init {
+        trace_written_init(&mut trait_items, &init_trace)?;
+    }
+    let init_ty = (!has_init_ty).then(|| quote! { type Init = (); });
+    let init_fn = (!has_init).then(|| {
+        quote! {
+            fn init(
+                _init: Self::Init,
+                root: Self::Root,
+                sender: ::kurage::relm4::ComponentSender<Self>,
+            ) -> ::kurage::relm4::ComponentParts<Self> {
+                #init_trace
+                let model = Self::default();
+                let widgets = view_output!();
+                ::kurage::relm4::ComponentParts { model, widgets }
+            }
+        }
+    });
+
+    let sender = format_ident!("sender");
+    let msg_decl = msg_enum_decl(&msg_enum, &handlers);
+    let arms = handlers.iter().map(|h| h.match_arm(&sender));
+    let self_ty = &item.self_ty;
+    let attrs = &item.attrs;
+    let (impl_generics, _, where_clause) = item.generics.split_for_impl();
+
+    Ok(quote! {
+        #msg_decl
+
+        #out_decl
+
+        #(#attrs)*
+        impl #impl_generics #self_ty #where_clause {
+            #(#handler_fns)*
+        }
+
+        #[::kurage::relm4::component(pub)]
+        impl #impl_generics ::kurage::relm4::SimpleComponent for #self_ty #where_clause {
+            #init_ty
+            type Input = #msg_enum;
+            type Output = #out_ty;
+
+            #init_fn
+
+            #[allow(unused_variables)]
+            fn update(&mut self, #message: Self::Input, #sender: ::kurage::relm4::ComponentSender<Self>) {
+                #update_trace
+                match #message {
+                    #(#arms)*
+                }
+            }
+
+            #(#trait_items)*
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_init_is_traced() {
+        let item = syn::parse_quote! {
+            impl Counter {
+                fn init(
+                    _init: Self::Init,
+                    root: Self::Root,
+                    sender: ComponentSender<Self>,
+                ) -> ComponentParts<Self> {
+                    let model = Self::default();
+                    let widgets = view_output!();
+                    ComponentParts { model, widgets }
+                }
+            }
+        };
+        let args = syn::parse_quote! { trace = debug };
+        let file: syn::File = syn::parse2(component(args, item).unwrap()).unwrap();
+        let init = file
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Impl(i) if i.trait_.is_some() => Some(&i.items),
+                _ => None,
+            })
+            .flatten()
+            .find_map(|item| match item {
+                syn::ImplItem::Fn(f) if f.sig.ident == "init" => Some(f),
+                _ => None,
+            })
+            .expect("init() in the SimpleComponent impl");
+        let guard = trace::guard(
+            Some(&syn::parse_quote! { debug }),
+            &format_ident!("Counter"),
+            "init",
+            &quote! { ::core::option::Option::None },
+        );
+        let first = init.block.stmts.first();
+        assert_eq!(quote! { #first }.to_string(), guard.to_string());
+        assert_eq!(init.block.stmts.len(), 4);
+    }
+}
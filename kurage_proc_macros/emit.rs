@@ -0,0 +1,147 @@
+use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
+use quote::quote;
+
+/// The name of the local macro that `emit Variant(…)` is turned into.
+pub const MACRO: &str = "kurage_emit";
+
+/// Whether `emit` may start an expression after `tt`: at the start of a group, after `=>`, `;`,
+/// a block, `=` or `,`, or as the body of a closure.
+fn starts_expr(tt: Option<&TokenTree>, after_arrow: bool) -> bool {
+    match tt {
+        None => true,
+        Some(TokenTree::Punct(p)) => matches!(p.as_char(), ';' | '=' | ',' | '|') || after_arrow,
+        Some(TokenTree::Group(g)) => g.delimiter() == Delimiter::Brace,
+        Some(_) => false,
+    }
+}
+
+/// Whether `tt` continues the path of a variant ending with `last`.
+fn continues_path(tt: &TokenTree, last: Option<&TokenTree>) -> bool {
+    match tt {
+        TokenTree::Punct(p) => p.as_char() == ':',
+        TokenTree::Ident(_) => matches!(last, Some(TokenTree::Punct(_))),
+        _ => false,
+    }
+}
+
+/// [`rewrite`] the contents of `group`.
+fn rewrite_group(group: &Group) -> TokenTree {
+    let mut new = Group::new(group.delimiter(), rewrite(group.stream()));
+    new.set_span(group.span());
+    TokenTree::Group(new)
+}
+
+/// Replace `emit Variant(…)` at the start of expressions in `tokens` by `kurage_emit!(Variant(…))`,
+/// recursing into groups.
+///
+/// The variant is a path optionally followed by `(…)` or `{ … }`.
+pub fn rewrite(tokens: TokenStream) -> TokenStream {
+    let mut out: Vec<TokenTree> = vec![];
+    let mut iter = tokens.into_iter().peekable();
+    let mut after_arrow = false;
+    while let Some(tt) = iter.next() {
+        let is_emit = matches!(&tt, TokenTree::Ident(i) if i == "emit")
+            && starts_expr(out.last(), after_arrow)
+            && matches!(iter.peek(), Some(TokenTree::Ident(i)) if i != "as" && i != "else");
+        after_arrow = matches!(
+            (out.last(), &tt),
+            (Some(TokenTree::Punct(eq)), TokenTree::Punct(gt))
+                if eq.as_char() == '=' && gt.as_char() == '>'
+        );
+        if !is_emit {
+            out.push(match tt {
+                TokenTree::Group(g) => rewrite_group(&g),
+                tt => tt,
+            });
+            continue;
+        }
+        let mut variant: Vec<TokenTree> = iter.next().into_iter().collect();
+        while let Some(next) = iter.next_if(|next| continues_path(next, variant.last())) {
+            variant.push(next);
+        }
+        if let Some(TokenTree::Group(g)) = iter.next_if(
+            |next| matches!(next, TokenTree::Group(g) if g.delimiter() != Delimiter::Bracket),
+        ) {
+            variant.push(rewrite_group(&g));
+        }
+        let mac = syn::Ident::new(MACRO, tt.span());
+        out.extend(quote! { #mac!(#(#variant)*) });
+    }
+    out.into_iter().collect()
+}
+
+/// The local macro sending `$output::$variant` with `sender`, declared in the functions
+/// containing the handlers.
+pub fn macro_decl(sender: &syn::Ident, comp: &syn::Ident, output: &TokenStream) -> TokenStream {
+    let mac = syn::Ident::new(MACRO, proc_macro2::Span::call_site());
+    let comp = comp.to_string();
+    quote! {
+        #[allow(unused_macros)]
+        macro_rules! #mac {
+            ($($variant:tt)*) => {
+                ::kurage::__private::emitted(#sender.output(#output::$($variant)*), #comp)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_rewrites(tokens: TokenStream, expected: &TokenStream) {
+        assert_eq!(rewrite(tokens).to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn whole_handler() {
+        assert_rewrites(quote! { emit Done }, &quote! { kurage_emit!(Done) });
+        assert_rewrites(
+            quote! { Next => emit Nav(NavAction::Next), },
+            &quote! { Next => kurage_emit!(Nav(NavAction::Next)), },
+        );
+    }
+
+    #[test]
+    fn statements() {
+        assert_rewrites(
+            quote! { { self.n += 1; emit Changed { n: self.n }; if x {} emit Done } },
+            &quote! { { self.n += 1; kurage_emit!(Changed { n: self.n }); if x {} kurage_emit!(Done) } },
+        );
+    }
+
+    #[test]
+    fn let_value() {
+        assert_rewrites(
+            quote! { let r = emit Moved(1); },
+            &quote! { let r = kurage_emit!(Moved(1)); },
+        );
+    }
+
+    #[test]
+    fn arguments() {
+        assert_rewrites(
+            quote! { foo(emit A, emit b::B(2)) },
+            &quote! { foo(kurage_emit!(A), kurage_emit!(b::B(2))) },
+        );
+    }
+
+    #[test]
+    fn closure_body() {
+        assert_rewrites(
+            quote! { xs.iter().for_each(|x| emit Item(*x)); go(|| emit Done) },
+            &quote! { xs.iter().for_each(|x| kurage_emit!(Item(*x))); go(|| kurage_emit!(Done)) },
+        );
+    }
+
+    #[test]
+    fn emit_as_a_variable() {
+        let tokens = quote! {
+            let emit = 1;
+            let x = emit as u8;
+            let Some(y) = emit else { return };
+            call(emit, emit.field);
+        };
+        assert_rewrites(tokens.clone(), &tokens);
+    }
+}
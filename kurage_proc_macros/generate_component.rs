@@ -0,0 +1,1686 @@
+use proc_macro2::{Delimiter, Group, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{ext::IdentExt, parse::ParseStream, punctuated::Punctuated, Token};
+
+use crate::{
+    attrs::ItemAttrs,
+    coalesce::{self, Coalesce},
+    emit,
+    generics::{self, ItemGenerics},
+    subscriptions::{self, Subscription},
+    trace,
+};
+
+const SECTIONS: [&str; 11] = [
+    "trace",
+    "children",
+    "subscriptions",
+    "model",
+    "preinit",
+    "init",
+    "shutdown",
+    "post_view",
+    "commands",
+    "reduce",
+    "update",
+];
+
+/// Input of [`crate::generate_component!`].
+pub struct GenerateComponent {
+    /// Attributes and visibility of the model, also the default visibility of the other items.
+    model_attrs: ItemAttrs,
+    comp: syn::Ident,
+    /// Generic parameters and the where clause after the component name.
+    generics: syn::Generics,
+    model: Option<syn::FieldsNamed>,
+    /// Fields of the model marked with `#[sensitive]`.
+    sensitive_fields: Vec<syn::Ident>,
+    /// How each field of the model is initialized.
+    field_inits: Vec<(syn::Ident, FieldInit)>,
+    trace: Option<trace::Level>,
+    /// Child components launched in `init()`, also added as fields to `model`.
+    children: Vec<Child>,
+    /// Timeouts registered in `init()` and removed in `shutdown()`.
+    subscriptions: Vec<Subscription>,
+    /// `model = expr;`, used instead of `Self::default()`.
+    model_expr: Option<syn::Expr>,
+    preinit: Option<Group>,
+    init: Option<InitSection>,
+    shutdown: Option<Hook>,
+    post_view: Option<Hook>,
+    commands: Option<(ItemAttrs, Vec<Handler>)>,
+    /// Attributes and visibility of `fn reduce()`, if the `reduce` section is given.
+    reduce: Option<ItemAttrs>,
+    /// Attributes and visibility of the Msg enum, written before `update()`.
+    msg_attrs: ItemAttrs,
+    update: UpdateSection,
+    out: Output,
+    view: TokenStream,
+}
+
+/// How a field of the model is initialized.
+enum FieldInit {
+    /// `field: Type`, using `Default::default()`
+    Default,
+    /// `field: Type = expr`
+    Expr(Box<syn::Expr>),
+    /// `#[from_init] field: Type`
+    FromInit(Span),
+    /// A child component in `children { … }`, launched into a local variable of the same name
+    Child,
+}
+
+/// `name: ChildComponent = init_expr => forward |msg| …`
+struct Child {
+    name: syn::Ident,
+    ty: syn::Type,
+    init: syn::Expr,
+    /// Turns the outputs of the child into messages of the component; the child is detached
+    /// otherwise.
+    forward: Option<syn::Expr>,
+}
+
+/// `init[local_refs](root, sender, model, widgets) for init: InitType { … }`
+struct InitSection {
+    local_refs: Vec<LocalRef>,
+    root: syn::Ident,
+    sender: syn::Ident,
+    model: syn::Ident,
+    widgets: syn::Ident,
+    init: Option<(syn::Ident, syn::Type)>,
+    block: Group,
+}
+
+/// An item inside `init[…]`.
+enum LocalRef {
+    /// `btn` → `let btn = &model.btn;`
+    Field(syn::Ident),
+    /// `btn { expr }` → `let btn = { expr };`
+    Expr(syn::Ident, Group),
+}
+
+/// `shutdown(self, widgets, output) { … }` or `post_view(self, widgets, sender) { … }`
+struct Hook {
+    selff: syn::Ident,
+    widgets: syn::Ident,
+    /// `output` for `shutdown()`, `sender` for `post_view()`
+    arg: syn::Ident,
+    block: Group,
+}
+
+/// `update(self, message, sender) { handlers }`
+struct UpdateSection {
+    selff: syn::Ident,
+    message: syn::Ident,
+    sender: syn::Ident,
+    handlers: Vec<Handler>,
+}
+
+/// `Variant(param: Type, …) => expr` or `Variant { field: Type, … } => expr`, optionally preceded
+/// by attributes for the variant
+struct Handler {
+    attrs: Vec<syn::Attribute>,
+    /// `#[debounce(…)]` or `#[throttle(…)]`, removed from `attrs`
+    coalesce: Option<Coalesce>,
+    variant: syn::Ident,
+    params: Option<Punctuated<Param, Token![,]>>,
+    /// Whether `params` are named fields in `{ … }`.
+    named: bool,
+    body: syn::Expr,
+}
+
+/// `param: Type` or `#[sensitive] param: Type`
+struct Param {
+    name: syn::Ident,
+    ty: syn::Type,
+    sensitive: bool,
+}
+
+/// What comes after `update(…) { … } =>`.
+enum Output {
+    /// `#[attrs] vis { Variant1, Variant2(Type) }`
+    Enum(ItemAttrs, TokenStream),
+    Type(syn::Type),
+}
+
+/// The number of single-character edits to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let mut prev: Vec<usize> = (0..=b.chars().count()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for ((cb, diag), up) in b.chars().zip(&prev).zip(prev.iter().skip(1)) {
+            let left = cur.last().copied().unwrap_or_default();
+            cur.push((diag + usize::from(ca != cb)).min(up + 1).min(left + 1));
+        }
+        prev = cur;
+    }
+    prev.last().copied().unwrap_or_default()
+}
+
+fn parse_ident_list<const N: usize>(group: &Group, usage: &str) -> syn::Result<[syn::Ident; N]> {
+    let idents = syn::parse::Parser::parse2(
+        |input: ParseStream| {
+            Punctuated::<syn::Ident, Token![,]>::parse_terminated_with(input, syn::Ident::parse_any)
+        },
+        group.stream(),
+    )?;
+    let len = idents.len();
+    idents
+        .into_iter()
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| {
+            syn::Error::new(
+                group.span(),
+                format!("kurage: expected {N} arguments `{usage}`, found {len}"),
+            )
+        })
+}
+
+fn parse_group(input: ParseStream, delimiter: Delimiter, expected: &str) -> syn::Result<Group> {
+    match input.parse::<TokenTree>() {
+        Ok(TokenTree::Group(g)) if g.delimiter() == delimiter => Ok(g),
+        Ok(tt) => Err(syn::Error::new(
+            tt.span(),
+            format!("kurage: expected {expected}"),
+        )),
+        Err(e) => Err(syn::Error::new(
+            e.span(),
+            format!("kurage: expected {expected}"),
+        )),
+    }
+}
+
+/// Remove `#[sensitive]` from `attrs`, returning whether it was there.
+fn take_sensitive(attrs: &mut Vec<syn::Attribute>) -> syn::Result<bool> {
+    take_marker(attrs, "sensitive")
+}
+
+/// Remove `#[name]` from `attrs`, returning whether it was there.
+fn take_marker(attrs: &mut Vec<syn::Attribute>, name: &str) -> syn::Result<bool> {
+    let len = attrs.len();
+    let mut res = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path().is_ident(name) {
+            return true;
+        }
+        if let Err(e) = attr.meta.require_path_only() {
+            res = Err(e);
+        }
+        false
+    });
+    res.map(|()| attrs.len() != len)
+}
+
+/// The `{ field: Type = default, … }` block of the model.
+#[derive(Default)]
+struct ModelBlock {
+    fields: Option<syn::FieldsNamed>,
+    sensitive_fields: Vec<syn::Ident>,
+    field_inits: Vec<(syn::Ident, FieldInit)>,
+}
+
+fn parse_model(input: ParseStream) -> syn::Result<ModelBlock> {
+    if !input.peek(syn::token::Brace) {
+        return Ok(ModelBlock::default());
+    }
+    let content;
+    let brace_token = syn::braced!(content in input);
+    let mut named = Punctuated::new();
+    let mut sensitive_fields = vec![];
+    let mut field_inits = vec![];
+    while !content.is_empty() {
+        let mut attrs = content.call(syn::Attribute::parse_outer)?;
+        let sensitive = take_sensitive(&mut attrs)?;
+        let from_init = take_marker(&mut attrs, "from_init")?;
+        let vis = content.parse()?;
+        let ident: syn::Ident = content.parse()?;
+        let colon_token = content.parse()?;
+        let ty = content.parse()?;
+        let init = match content.parse::<Option<Token![=]>>()? {
+            Some(eq) if from_init => {
+                return Err(syn::Error::new(
+                    eq.span,
+                    "kurage: #[from_init] fields can't have a default",
+                ))
+            }
+            Some(_) => FieldInit::Expr(Box::new(content.parse()?)),
+            None if from_init => FieldInit::FromInit(ident.span()),
+            None => FieldInit::Default,
+        };
+        if sensitive {
+            sensitive_fields.push(ident.clone());
+        }
+        field_inits.push((ident.clone(), init));
+        named.push(syn::Field {
+            attrs,
+            vis,
+            mutability: syn::FieldMutability::None,
+            ident: Some(ident),
+            colon_token: Some(colon_token),
+            ty,
+        });
+        if content.is_empty() {
+            break;
+        }
+        content.parse::<Token![,]>()?;
+    }
+    Ok(ModelBlock {
+        fields: Some(syn::FieldsNamed { brace_token, named }),
+        sensitive_fields,
+        field_inits,
+    })
+}
+
+impl syn::parse::Parse for Param {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attrs = input.call(syn::Attribute::parse_outer)?;
+        let sensitive = take_sensitive(&mut attrs)?;
+        if let Some(attr) = attrs.first() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "kurage: only `#[sensitive]` is allowed on message parameters",
+            ));
+        }
+        let name: syn::Ident = input.parse()?;
+        if input.parse::<Option<Token![:]>>()?.is_none() {
+            return Err(syn::Error::new(
+                name.span(),
+                "kurage: expected `name: Type` for message parameters",
+            ));
+        }
+        Ok(Self {
+            name,
+            ty: input.parse()?,
+            sensitive,
+        })
+    }
+}
+
+impl syn::parse::Parse for Child {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let init = input.parse()?;
+        let forward = if input.parse::<Option<Token![=>]>>()?.is_some() {
+            let kw: syn::Ident = input.parse()?;
+            if kw != "forward" {
+                return Err(syn::Error::new(
+                    kw.span(),
+                    "kurage: expected `forward |msg| …` after `=>`",
+                ));
+            }
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        Ok(Self {
+            name,
+            ty,
+            init,
+            forward,
+        })
+    }
+}
+
+impl Child {
+    /// Parse a comma-separated list of children inside `{ … }`.
+    fn parse_list(input: ParseStream) -> syn::Result<Vec<Self>> {
+        let content;
+        syn::braced!(content in input);
+        let children = content.parse_terminated(<Self as syn::parse::Parse>::parse, Token![,])?;
+        Ok(children.into_iter().collect())
+    }
+
+    /// The field of the model holding the controller of the child.
+    fn field(&self) -> syn::Field {
+        let Self { name, ty, .. } = self;
+        syn::parse_quote! { #name: ::kurage::relm4::Controller<#ty> }
+    }
+
+    /// `let name = …;` launching the child.
+    fn launch(&self, sender: &syn::Ident) -> TokenStream {
+        let Self {
+            name,
+            ty,
+            init,
+            forward,
+        } = self;
+        let connect = forward.as_ref().map_or_else(
+            || quote! { .detach() },
+            |forward| quote! { .forward(#sender.input_sender(), #forward) },
+        );
+        quote! {
+            let #name = <#ty as ::kurage::relm4::Component>::builder()
+                .launch(#init)
+                #connect;
+        }
+    }
+}
+
+impl syn::parse::Parse for Handler {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attrs = input.call(syn::Attribute::parse_outer)?;
+        let coalesce = coalesce::take(&mut attrs)?;
+        let variant = input.parse()?;
+        let named = input.peek(syn::token::Brace);
+        let params = if input.peek(syn::token::Paren) {
+            let content;
+            syn::parenthesized!(content in input);
+            Some(content.parse_terminated(Param::parse, Token![,])?)
+        } else if named {
+            let content;
+            syn::braced!(content in input);
+            Some(content.parse_terminated(Param::parse, Token![,])?)
+        } else {
+            None
+        };
+        input.parse::<Token![=>]>()?;
+        Ok(Self {
+            attrs,
+            coalesce,
+            variant,
+            params,
+            named,
+            body: input.parse()?,
+        })
+    }
+}
+
+impl Handler {
+    /// Parse a comma-separated list of handlers inside `{ … }`.
+    fn parse_list(group: &Group) -> syn::Result<Vec<Self>> {
+        syn::parse::Parser::parse2(
+            |input: ParseStream| {
+                let mut handlers = vec![];
+                while !input.is_empty() {
+                    let handler: Self = input.parse()?;
+                    let block_like = matches!(
+                        handler.body,
+                        syn::Expr::Block(_) | syn::Expr::If(_) | syn::Expr::Match(_)
+                    );
+                    handlers.push(handler);
+                    if input.is_empty() {
+                        break;
+                    }
+                    if input.parse::<Option<Token![,]>>()?.is_none() && !block_like {
+                        return Err(input.error("kurage: expected `,` after message handler"));
+                    }
+                }
+                Ok(handlers)
+            },
+            emit::rewrite(group.stream()),
+        )
+    }
+
+    fn variant_decl(&self) -> TokenStream {
+        let Self {
+            attrs,
+            variant,
+            params,
+            named,
+            ..
+        } = self;
+        let names = params.iter().flatten().map(|p| &p.name);
+        let types = params.iter().flatten().map(|p| &p.ty);
+        let fields = match (params, named) {
+            (None, _) => None,
+            (Some(_), false) => Some(quote! { (#(#types),*) }),
+            (Some(_), true) => Some(quote! { { #(#names: #types),* } }),
+        };
+        quote! { #(#attrs)* #variant #fields }
+    }
+
+    /// The `#[cfg]` attributes of the variant, which also apply to the arms matching it.
+    fn cfg_attrs(&self) -> TokenStream {
+        let cfgs = (self.attrs.iter()).filter(|attr| attr.path().is_ident("cfg"));
+        quote! { #(#cfgs)* }
+    }
+
+    /// The pattern matching the variant of `ty`, binding its fields by name.
+    fn pattern(&self, ty: &TokenStream) -> TokenStream {
+        let Self {
+            variant,
+            params,
+            named,
+            ..
+        } = self;
+        let names = params.iter().flatten().map(|p| &p.name);
+        match (params, named) {
+            (None, _) => quote! { #ty::#variant },
+            (Some(_), false) => quote! { #ty::#variant(#(#names),*) },
+            (Some(_), true) => quote! { #ty::#variant { #(#names),* } },
+        }
+    }
+
+    fn is_sensitive(&self) -> bool {
+        self.params.iter().flatten().any(|p| p.sensitive)
+    }
+
+    /// The arm of `match self` in a `Debug` impl, printing `<redacted>` for sensitive params.
+    fn debug_arm(&self) -> TokenStream {
+        let Self {
+            variant,
+            params,
+            named,
+            ..
+        } = self;
+        let cfgs = self.cfg_attrs();
+        let pat = self.pattern(&quote! { Self });
+        let Some(params) = params else {
+            return quote! { #cfgs #pat => f.write_str(::core::stringify!(#variant)) };
+        };
+        let fields = params.iter().map(
+            |Param {
+                 name, sensitive, ..
+             }| {
+                let value = if *sensitive {
+                    quote! { &::kurage::__private::Redacted }
+                } else {
+                    quote! { #name }
+                };
+                if *named {
+                    quote! { .field(::core::stringify!(#name), #value) }
+                } else {
+                    quote! { .field(#value) }
+                }
+            },
+        );
+        let debug = if *named {
+            quote! { debug_struct }
+        } else {
+            quote! { debug_tuple }
+        };
+        quote! {
+            #cfgs
+            #[allow(unused_variables)]
+            #pat => f
+                .#debug(::core::stringify!(#variant))
+                #(#fields)*
+                .finish()
+        }
+    }
+
+    fn match_arm(&self, ty: &TokenStream) -> TokenStream {
+        let cfgs = self.cfg_attrs();
+        let pat = self.pattern(ty);
+        let body = &self.body;
+        quote! { #cfgs #pat => #body }
+    }
+}
+
+/// The generics of the enum with `handlers` as variants.
+fn enum_generics(handlers: &[Handler], comp_generics: &syn::Generics) -> ItemGenerics {
+    let variants = handlers.iter().map(Handler::variant_decl);
+    ItemGenerics::new(comp_generics, &quote! { #(#variants)* }, false)
+}
+
+/// The declaration of an enum with `handlers` as variants, and the enum type with its generics.
+/// `Debug` is implemented manually instead of derived if some params are `#[sensitive]`.
+fn enum_decl(
+    name: &syn::Ident,
+    (item_attrs, default_vis): (&ItemAttrs, &TokenStream),
+    derives: &[&str],
+    handlers: &[Handler],
+    comp_generics: &syn::Generics,
+) -> (TokenStream, TokenStream, ItemGenerics) {
+    let variants = handlers.iter().map(Handler::variant_decl);
+    let item_generics = enum_generics(handlers, comp_generics);
+    let generics = &item_generics.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let phantom = item_generics.phantom_variant();
+    let mut derives = derives.to_vec();
+    let debug = if !item_attrs.derives("Debug") {
+        None
+    } else if handlers.iter().any(Handler::is_sensitive) {
+        let arms = handlers.iter().map(Handler::debug_arm);
+        let phantom_arm = item_generics.phantom_arm(&quote! { Self }, true);
+        let bounded_where = item_generics.bounded_where(&quote! { ::core::fmt::Debug });
+        Some(quote! {
+            impl #impl_generics ::core::fmt::Debug for #name #ty_generics #bounded_where {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    match self {
+                        #(#arms,)*
+                        #phantom_arm
+                    }
+                }
+            }
+        })
+    } else {
+        derives.push("Debug");
+        None
+    };
+    let attrs = item_attrs.to_tokens(&derives, default_vis);
+    let decl = quote! {
+        #[allow(dead_code)]
+        #attrs
+        enum #name #generics #where_clause {
+            #(#variants,)*
+            #phantom
+        }
+        #debug
+    };
+    let ty = quote! { #name #ty_generics };
+    (decl, ty, item_generics)
+}
+
+impl InitSection {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let local_refs = if input.peek(syn::token::Bracket) {
+            let content;
+            syn::bracketed!(content in input);
+            let mut local_refs = vec![];
+            while !content.is_empty() {
+                let ident = content.parse()?;
+                local_refs.push(if content.peek(syn::token::Brace) {
+                    LocalRef::Expr(ident, content.parse()?)
+                } else {
+                    LocalRef::Field(ident)
+                });
+            }
+            local_refs
+        } else {
+            vec![]
+        };
+        let args = parse_group(
+            input,
+            Delimiter::Parenthesis,
+            "`(root, sender, model, widgets)`",
+        )?;
+        let [root, sender, model, widgets] =
+            parse_ident_list(&args, "(root, sender, model, widgets)")?;
+        let init = if input.parse::<Option<Token![for]>>()?.is_some() {
+            let ident = input.parse()?;
+            input.parse::<Token![:]>()?;
+            Some((ident, input.parse()?))
+        } else {
+            None
+        };
+        Ok(Self {
+            local_refs,
+            root,
+            sender,
+            model,
+            widgets,
+            init,
+            block: parse_group(input, Delimiter::Brace, "{ … } for the init() block")?,
+        })
+    }
+}
+
+impl Hook {
+    fn parse(input: ParseStream, usage: &str) -> syn::Result<Self> {
+        let args = parse_group(input, Delimiter::Parenthesis, &format!("`{usage}`"))?;
+        let [selff, widgets, arg] = parse_ident_list(&args, usage)?;
+        Ok(Self {
+            selff,
+            widgets,
+            arg,
+            block: parse_group(input, Delimiter::Brace, "{ … }")?,
+        })
+    }
+}
+
+impl UpdateSection {
+    fn parse(input: ParseStream) -> syn::Result<(Self, Output)> {
+        let args = parse_group(input, Delimiter::Parenthesis, "`(self, message, sender)`")?;
+        let [selff, message, sender] = parse_ident_list(&args, "(self, message, sender)")?;
+        let mut handlers = Handler::parse_list(&parse_group(
+            input,
+            Delimiter::Brace,
+            "{ … } for the update() block",
+        )?)?;
+        if handlers.iter().any(|h| h.coalesce.is_some()) {
+            handlers.push(syn::parse2(coalesce::release_handler())?);
+        }
+        if input.parse::<Option<Token![=>]>>()?.is_none() {
+            return Err(input.error("kurage: expected `=> {}` or `=> Type` after update()"));
+        }
+        let out_attrs: ItemAttrs = input.parse()?;
+        let out = if input.peek(syn::token::Brace) {
+            Output::Enum(
+                out_attrs,
+                parse_group(input, Delimiter::Brace, "{ … }")?.stream(),
+            )
+        } else if !out_attrs.is_empty() {
+            return Err(input.error("kurage: expected `{ … }` after the attributes of the Output"));
+        } else {
+            let span = input.span();
+            Output::Type(input.parse().map_err(|_| {
+                syn::Error::new(span, "kurage: Output block must be `{}` or a type")
+            })?)
+        };
+        Ok((
+            Self {
+                selff,
+                message,
+                sender,
+                handlers,
+            },
+            out,
+        ))
+    }
+}
+
+/// The error for `section`, which is not a known section, unless it starts the path of the root
+/// widget of the view.
+fn unknown_section(input: ParseStream, section: &syn::Ident) -> syn::Error {
+    if input.peek2(Token![::]) {
+        return syn::Error::new(
+            section.span(),
+            "kurage: update() is required before the view",
+        );
+    }
+    let name = section.to_string();
+    let hint = SECTIONS
+        .iter()
+        .find(|s| edit_distance(s, &name) <= 2)
+        .map_or_else(
+            || {
+                let sections = SECTIONS.map(|s| format!("`{s}`")).join(", ");
+                format!("expected one of {sections}")
+            },
+            |s| format!("did you mean `{s}`?"),
+        );
+    syn::Error::new(
+        section.span(),
+        format!("kurage: unknown section `{name}`, {hint}"),
+    )
+}
+
+/// Parse the name of the next section, checking that it is known and not given twice.
+fn section_name(
+    input: ParseStream,
+    item_attrs: &ItemAttrs,
+    seen: &mut Vec<String>,
+) -> syn::Result<String> {
+    if input.is_empty() {
+        return Err(syn::Error::new(
+            input.span(),
+            "kurage: update() is required",
+        ));
+    }
+    let Ok(section) = input.fork().call(syn::Ident::parse_any) else {
+        return Err(input.error("kurage: update() is required before the view"));
+    };
+    let name = section.to_string();
+    if !SECTIONS.contains(&name.as_str()) {
+        return Err(unknown_section(input, &section));
+    }
+    if seen.contains(&name) {
+        return Err(syn::Error::new(
+            section.span(),
+            format!("kurage: duplicate section `{name}`"),
+        ));
+    }
+    if !item_attrs.is_empty() && !["commands", "reduce", "update"].contains(&name.as_str()) {
+        return Err(syn::Error::new(
+            section.span(),
+            "kurage: attributes and visibility are only allowed before `commands`, `reduce` and \
+             `update()`",
+        ));
+    }
+    input.call(syn::Ident::parse_any)?;
+    seen.push(name.clone());
+    Ok(name)
+}
+
+impl syn::parse::Parse for GenerateComponent {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let model_attrs = input.parse()?;
+        let comp = input.parse()?;
+        let mut generics: syn::Generics = input.parse()?;
+        generics.where_clause = input.parse()?;
+        let ModelBlock {
+            fields: mut model,
+            sensitive_fields,
+            mut field_inits,
+        } = parse_model(input)?;
+        input.parse::<Token![:]>()?;
+
+        let mut trace = None;
+        let mut children = None;
+        let mut subscriptions = vec![];
+        let mut model_expr = None;
+        let mut preinit = None;
+        let mut init = None;
+        let mut shutdown = None;
+        let mut post_view = None;
+        let mut commands = None;
+        let mut reduce = None;
+        let mut seen = vec![];
+        let (msg_attrs, (update, out)) = loop {
+            let item_attrs: ItemAttrs = input.parse()?;
+            match section_name(input, &item_attrs, &mut seen)?.as_str() {
+                "trace" => {
+                    let content;
+                    syn::parenthesized!(content in input);
+                    trace = Some(content.parse()?);
+                }
+                "children" => children = Some(Child::parse_list(input)?),
+                "subscriptions" => subscriptions = subscriptions::parse_list(input)?,
+                "model" => {
+                    input.parse::<Token![=]>()?;
+                    model_expr = Some(input.parse()?);
+                    input.parse::<Token![;]>()?;
+                }
+                "preinit" => preinit = Some(parse_group(input, Delimiter::Brace, "{ … }")?),
+                "init" => init = Some(InitSection::parse(input)?),
+                "shutdown" => shutdown = Some(Hook::parse(input, "(self, widgets, output)")?),
+                "post_view" => post_view = Some(Hook::parse(input, "(self, widgets, sender)")?),
+                "commands" => {
+                    let handlers = Handler::parse_list(&parse_group(
+                        input,
+                        Delimiter::Brace,
+                        "{ … } for the commands block",
+                    )?)?;
+                    if let Some(h) = handlers.iter().find(|h| h.coalesce.is_some()) {
+                        return Err(syn::Error::new(
+                            h.variant.span(),
+                            "kurage: #[debounce] and #[throttle] only apply to update() handlers",
+                        ));
+                    }
+                    commands = Some((item_attrs, handlers));
+                }
+                "reduce" => reduce = Some(item_attrs),
+                _ => break (item_attrs, UpdateSection::parse(input)?),
+            }
+        };
+        let children = children.unwrap_or_default();
+        for child in &children {
+            (model.get_or_insert_with(|| syn::parse_quote!({})).named).push(child.field());
+            field_inits.push((child.name.clone(), FieldInit::Child));
+        }
+        let this = Self {
+            model_attrs,
+            comp,
+            generics,
+            model,
+            sensitive_fields,
+            field_inits,
+            trace,
+            children,
+            subscriptions,
+            model_expr,
+            preinit,
+            init,
+            shutdown,
+            post_view,
+            commands,
+            reduce,
+            msg_attrs,
+            update,
+            out,
+            view: input.parse()?,
+        };
+        this.validate()?;
+        Ok(this)
+    }
+}
+
+impl GenerateComponent {
+    /// Check the combination of sections.
+    fn validate(&self) -> syn::Result<()> {
+        if let (Some(preinit), None) = (&self.preinit, &self.init) {
+            return Err(syn::Error::new(
+                preinit.span(),
+                "kurage: preinit requires init()",
+            ));
+        }
+        let from_init = self.field_inits.iter().find_map(|(_, init)| match init {
+            FieldInit::FromInit(span) => Some(*span),
+            _ => None,
+        });
+        if let (Some(_), Some(h)) = (&self.model_expr, self.coalesced().next()) {
+            return Err(syn::Error::new(
+                h.variant.span(),
+                "kurage: #[debounce] and #[throttle] can't be used with `model = …;`",
+            ));
+        }
+        if self.model_expr.is_some() {
+            let model = &self.model;
+            if let Some(param) = generics::first_unused(&self.generics, &quote! { #model }) {
+                return Err(syn::Error::new_spanned(
+                    param,
+                    "kurage: with `model = …;`, the fields of the model must use all the generic \
+                     parameters, e.g. in a `PhantomData` field",
+                ));
+            }
+        }
+        if let Some(span) = from_init {
+            if self.model_expr.is_some() {
+                return Err(syn::Error::new(
+                    span,
+                    "kurage: #[from_init] can't be used with `model = …;`",
+                ));
+            }
+            if self.init.as_ref().is_none_or(|init| init.init.is_none()) {
+                return Err(syn::Error::new(
+                    span,
+                    "kurage: #[from_init] requires `init(…) for name: InitType`",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn init_fn(&self) -> TokenStream {
+        let sender = &self.update.sender;
+        let (root, init, model, widgets) = (
+            format_ident!("root"),
+            format_ident!("init"),
+            format_ident!("model"),
+            format_ident!("widgets"),
+        );
+        let trace = trace::guard(
+            self.trace.as_ref(),
+            &self.comp,
+            "init",
+            &quote! { ::core::option::Option::None },
+        );
+        let init_ident = (self.init.as_ref())
+            .and_then(|init| init.init.as_ref())
+            .map(|(ident, _)| ident);
+        let model_expr = match &self.model_expr {
+            Some(expr) => quote! { #expr },
+            None if self.needs_ctor() => {
+                self.model_ctor(self.model_fields().0.as_ref(), init_ident)
+            }
+            None => quote! { Self::default() },
+        };
+        let children = self.children.iter().map(|child| child.launch(sender));
+        let subscriptions = (!self.subscriptions.is_empty()).then(|| {
+            let msg = format_ident!("{}Msg", self.comp);
+            subscriptions::register(&self.subscriptions, &msg, sender)
+        });
+        let Some(init_sect) = &self.init else {
+            return quote! {
+                #[allow(unused_variables)]
+                fn init(
+                    #init: Self::Init,
+                    #root: Self::Root,
+                    #sender: ::kurage::relm4::ComponentSender<Self>,
+                ) -> ::kurage::relm4::ComponentParts<Self> {
+                    #trace
+                    #(#children)*
+                    let #model = #model_expr;
+                    #subscriptions
+                    let #widgets = view_output!();
+                    ::kurage::relm4::ComponentParts { #model, #widgets }
+                }
+            };
+        };
+        let InitSection {
+            local_refs,
+            root: initroot,
+            sender: initsender,
+            model: initmodel,
+            widgets: initwidgets,
+            init: initvar,
+            block,
+        } = init_sect;
+        let local_refs = local_refs.iter().map(|lr| match lr {
+            LocalRef::Field(ident) => quote! { let #ident = &#initmodel.#ident; },
+            LocalRef::Expr(ident, expr) => quote! { let #ident = #expr; },
+        });
+        let initvar = initvar
+            .as_ref()
+            .map(|(ident, _)| quote! { let #ident = #init; });
+        let preinit = self.preinit.as_ref().map(Group::stream);
+        quote! {
+            #[allow(clippy::used_underscore_binding)]
+            #[allow(unused_variables)]
+            fn init(
+                #init: Self::Init,
+                #root: Self::Root,
+                #sender: ::kurage::relm4::ComponentSender<Self>,
+            ) -> ::kurage::relm4::ComponentParts<Self> {
+                #trace
+                #initvar
+                #(#children)*
+                #[allow(unused_mut)]
+                let mut #model = #model_expr;
+                #[allow(unused_mut, unused_assignments)]
+                let mut #initmodel = #model;
+
+                #(#local_refs)*
+
+                let #initroot = #root.clone();
+                #preinit
+
+                #subscriptions
+                let #widgets = view_output!();
+
+                let #initwidgets = #widgets;
+                #[allow(unused_variables)]
+                let #initsender = #sender;
+
+                #block
+
+                let #model = #initmodel;
+                let #widgets = #initwidgets;
+
+                ::kurage::relm4::ComponentParts { #model, #widgets }
+            }
+        }
+    }
+
+    fn trace_guard(
+        &self,
+        handler: &str,
+        ty: &TokenStream,
+        handlers: &[Handler],
+        generics: &ItemGenerics,
+    ) -> TokenStream {
+        let message = &self.update.message;
+        let variant = trace::variant_name(
+            message,
+            ty,
+            handlers.iter().map(|h| (h.cfg_attrs(), &h.variant)),
+            generics.phantom_arm(ty, true).as_ref(),
+        );
+        trace::guard(self.trace.as_ref(), &self.comp, handler, &variant)
+    }
+
+    /// The trait to implement, `fn update()` (and friends), and the command enum if any.
+    fn update_items(
+        &self,
+        msg_generics: &ItemGenerics,
+    ) -> (TokenStream, TokenStream, Option<TokenStream>) {
+        let Self {
+            comp,
+            generics,
+            commands,
+            update,
+            ..
+        } = self;
+        let UpdateSection {
+            selff,
+            message,
+            sender,
+            handlers,
+        } = update;
+        let msg_trace = self.trace_guard("update", &quote! { Self::Input }, handlers, msg_generics);
+        let emit = emit::macro_decl(sender, comp, &self.output_path());
+        let hold_back = self.hold_back();
+        let msg_body = if self.reduce.is_some() {
+            let comp = comp.to_string();
+            quote! {
+                let kurage_mock = ::kurage::reduce::MockSender::default();
+                #selff.reduce_with(#message, &kurage_mock);
+                kurage_mock.forward(&#sender, #comp);
+            }
+        } else {
+            self.msg_match(msg_generics)
+        };
+        let update_fn_sig = quote! {
+            fn update(
+                &mut #selff,
+                #message: Self::Input,
+                #sender: ::kurage::relm4::ComponentSender<Self>,
+                _root: &Self::Root,
+            )
+        };
+        if let Some((cmd_attrs, cmds)) = commands {
+            let (cmd_decl, cmd_ty, cmd_generics) = enum_decl(
+                &format_ident!("{comp}Cmd"),
+                (cmd_attrs, &self.default_vis()),
+                &[],
+                cmds,
+                generics,
+            );
+            let cmd_arms = cmds
+                .iter()
+                .map(|h| h.match_arm(&quote! { Self::CommandOutput }));
+            let cmd_phantom_arm = cmd_generics.phantom_arm(&quote! { Self::CommandOutput }, false);
+            let reset = self.reset_tracker();
+            let cmd_trace = self.trace_guard(
+                "update_cmd",
+                &quote! { Self::CommandOutput },
+                cmds,
+                &cmd_generics,
+            );
+            (
+                quote! { ::kurage::relm4::Component },
+                quote! {
+                    type CommandOutput = #cmd_ty;
+
+                    #update_fn_sig {
+                        #hold_back
+                        #msg_trace
+                        #msg_body
+                    }
+
+                    #[allow(unused_variables)]
+                    fn update_cmd(
+                        &mut #selff,
+                        #message: Self::CommandOutput,
+                        #sender: ::kurage::relm4::ComponentSender<Self>,
+                        _root: &Self::Root,
+                    ) {
+                        #cmd_trace
+                        #reset
+                        #emit
+                        match #message {
+                            #(#cmd_arms,)*
+                            #cmd_phantom_arm
+                        }
+                    }
+                },
+                Some(cmd_decl),
+            )
+        } else {
+            (
+                quote! { ::kurage::relm4::SimpleComponent },
+                quote! {
+                    fn update(
+                        &mut #selff,
+                        #message: Self::Input,
+                        #sender: ::kurage::relm4::ComponentSender<Self>,
+                    ) {
+                        #hold_back
+                        #msg_trace
+                        #msg_body
+                    }
+                },
+                None,
+            )
+        }
+    }
+
+    /// The `match` calling the handlers of `update()`.
+    fn msg_match(&self, msg_generics: &ItemGenerics) -> TokenStream {
+        let UpdateSection {
+            message,
+            sender,
+            handlers,
+            ..
+        } = &self.update;
+        let emit = emit::macro_decl(sender, &self.comp, &self.output_path());
+        // not `Self::Input`, which is ambiguous in `fn reduce_with()` outside of the trait impl
+        let msg = format_ident!("{}Msg", self.comp);
+        let ty = quote! { #msg };
+        let msg_arms = handlers.iter().map(|h| h.match_arm(&ty));
+        let msg_phantom_arm = msg_generics.phantom_arm(&ty, false);
+        let reset = self.reset_tracker();
+        quote! {
+            #reset
+            #emit
+            match #message {
+                #(#msg_arms,)*
+                #msg_phantom_arm
+            }
+        }
+    }
+
+    /// `fn reduce()` and `fn reduce_with()` if the `reduce` section is given, running the handlers
+    /// of `update()` with a [`MockSender`](::kurage::reduce::MockSender).
+    fn reduce_fns(&self, msg_generics: &ItemGenerics) -> Option<TokenStream> {
+        let reduce_attrs = self.reduce.as_ref()?;
+        let UpdateSection {
+            selff,
+            message,
+            sender,
+            ..
+        } = &self.update;
+        let attrs = &reduce_attrs.attrs;
+        let vis = reduce_attrs
+            .vis
+            .as_ref()
+            .map_or_else(|| self.default_vis(), |vis| quote! { #vis });
+        let msg_match = self.msg_match(msg_generics);
+        let input = quote! { <Self as ::kurage::relm4::Component>::Input };
+        let output = quote! { <Self as ::kurage::relm4::Component>::Output };
+        Some(quote! {
+            /// Handle `message` like `update()` but without GTK, returning the outputs sent.
+            #(#attrs)*
+            #vis fn reduce(&mut self, message: #input) -> ::std::vec::Vec<#output> {
+                let sender = ::kurage::reduce::MockSender::default();
+                self.reduce_with(message, &sender);
+                sender.take_outputs()
+            }
+
+            /// Handle `message` like `update()` but without GTK, recording the inputs and outputs
+            /// sent in `sender`.
+            #[allow(unused_variables)]
+            #vis fn reduce_with(
+                &mut #selff,
+                #message: #input,
+                #sender: &::kurage::reduce::MockSender<Self>,
+            ) {
+                #msg_match
+            }
+        })
+    }
+
+    /// `fn shutdown()` and `fn post_view()` for the trait impl, and the inherent method holding
+    /// the body of `post_view()`.
+    ///
+    /// relm4 inlines `fn post_view()` into `update_view()` where the names of the arguments are
+    /// fixed, so the body is moved into a method taking the names given in the macro instead.
+    fn hooks(&self) -> (TokenStream, Option<TokenStream>) {
+        let subscribed = !self.subscriptions.is_empty();
+        let shutdown = (self.shutdown.is_some() || subscribed).then(|| {
+            let (selff, widgets, arg) = self.shutdown.as_ref().map_or_else(
+                || {
+                    (
+                        format_ident!("self"),
+                        format_ident!("widgets"),
+                        format_ident!("output"),
+                    )
+                },
+                |hook| (hook.selff.clone(), hook.widgets.clone(), hook.arg.clone()),
+            );
+            let block = self.shutdown.as_ref().map(|hook| &hook.block);
+            let clear = subscribed.then(|| subscriptions::clear(&widgets));
+            let trace = trace::guard(
+                self.trace.as_ref(),
+                &self.comp,
+                "shutdown",
+                &quote! { ::core::option::Option::None },
+            );
+            quote! {
+                #[allow(unused_variables)]
+                fn shutdown(
+                    &mut #selff,
+                    #widgets: &mut Self::Widgets,
+                    #arg: ::kurage::relm4::Sender<Self::Output>,
+                ) {
+                    #trace
+                    #clear
+                    #block
+                }
+            }
+        });
+        let Some(Hook {
+            selff,
+            widgets,
+            arg,
+            block,
+        }) = &self.post_view
+        else {
+            return (quote! { #shutdown }, None);
+        };
+        let post_view = quote! {
+            fn post_view() {
+                Self::kurage_post_view(self, widgets, sender);
+            }
+        };
+        let inherent = quote! {
+            #[doc(hidden)]
+            #[allow(unused_variables, clippy::needless_pass_by_value)]
+            fn kurage_post_view(
+                &#selff,
+                #widgets: &mut <Self as ::kurage::relm4::Component>::Widgets,
+                #arg: ::kurage::relm4::ComponentSender<Self>,
+            ) #block
+        };
+        (quote! { #shutdown #post_view }, Some(inherent))
+    }
+
+    /// Whether the model has `#[tracker::track]`, which adds a `tracker` field to it.
+    fn tracked(&self) -> bool {
+        self.model_attrs.attrs.iter().any(|attr| {
+            let mut segments = attr.path().segments.iter().rev();
+            matches!(
+                (segments.next(), segments.next()),
+                (Some(track), Some(tracker)) if track.ident == "track" && tracker.ident == "tracker"
+            )
+        })
+    }
+
+    /// `self.reset();` to clear the changes tracked since the last update, if tracked.
+    fn reset_tracker(&self) -> Option<TokenStream> {
+        let selff = &self.update.selff;
+        self.tracked().then(|| quote! { #selff.reset(); })
+    }
+
+    /// The handlers of `update()` with `#[debounce]` or `#[throttle]`.
+    fn coalesced(&self) -> impl Iterator<Item = &Handler> {
+        (self.update.handlers.iter()).filter(|h| h.coalesce.is_some())
+    }
+
+    /// The statement holding back the messages of the handlers with `#[debounce]` or
+    /// `#[throttle]`, before the trace guard so that only the messages handled are traced.
+    fn hold_back(&self) -> Option<TokenStream> {
+        let UpdateSection {
+            selff,
+            message,
+            sender,
+            ..
+        } = &self.update;
+        let mut coalesced = self
+            .coalesced()
+            .filter_map(|h| Some((h.cfg_attrs(), &h.variant, h.coalesce.as_ref()?)))
+            .peekable();
+        coalesced.peek()?;
+        Some(coalesce::hold_back(
+            coalesced,
+            &format_ident!("{}Msg", self.comp),
+            (selff, message, sender),
+        ))
+    }
+
+    /// Whether the model must be built from its fields, i.e. some of them can't be defaulted.
+    fn needs_ctor(&self) -> bool {
+        (self.field_inits.iter())
+            .any(|(_, init)| matches!(init, FieldInit::FromInit(_) | FieldInit::Child))
+    }
+
+    /// The fields of the model, including a `PhantomData` for unused generic parameters.
+    fn model_fields(&self) -> (Option<syn::FieldsNamed>, ItemGenerics) {
+        let model = &self.model;
+        let item_generics = ItemGenerics::new(&self.generics, &quote! { #model }, true);
+        let mut fields = model.clone();
+        if let Some(phantom) = &item_generics.phantom {
+            fields
+                .get_or_insert_with(|| syn::parse_quote!({}))
+                .named
+                .push(syn::parse_quote! { __kurage_phantom: #phantom });
+        }
+        if self.coalesced().next().is_some() {
+            let field = format_ident!("{}", coalesce::FIELD);
+            let msg = format_ident!("{}Msg", self.comp);
+            let msg_generics = enum_generics(&self.update.handlers, &self.generics);
+            let (_, ty_generics, _) = msg_generics.generics.split_for_impl();
+            fields
+                .get_or_insert_with(|| syn::parse_quote!({}))
+                .named
+                .push(syn::parse_quote! {
+                    #field: ::kurage::coalesce::Coalesce<#msg #ty_generics>
+                });
+        }
+        if self.tracked() {
+            // the tracker needs named fields, and can't compare controllers or `PhantomData`
+            let fields = fields.get_or_insert_with(|| syn::parse_quote!({}));
+            for field in &mut fields.named {
+                let name = field.ident.as_ref();
+                if name.is_some_and(|name| name == "__kurage_phantom" || name == coalesce::FIELD)
+                    || (self.children.iter()).any(|child| Some(&child.name) == name)
+                {
+                    field.attrs.push(syn::parse_quote! { #[do_not_track] });
+                }
+            }
+        }
+        (fields, item_generics)
+    }
+
+    /// `Self { field: value, … }` using the defaults of the fields. `#[from_init]` fields are
+    /// taken from `initvar`, or from its field of the same name if the names differ, and children
+    /// from the local variables they are launched into.
+    fn model_ctor(
+        &self,
+        fields: Option<&syn::FieldsNamed>,
+        initvar: Option<&syn::Ident>,
+    ) -> TokenStream {
+        let Some(fields) = fields else {
+            return quote! { Self };
+        };
+        let values = fields.named.iter().flat_map(|f| &f.ident).map(|name| {
+            let init = (self.field_inits.iter()).find_map(|(n, init)| (n == name).then_some(init));
+            let value = match (init, initvar) {
+                (Some(FieldInit::Expr(expr)), _) => quote! { #expr },
+                (Some(FieldInit::FromInit(span)), Some(initvar)) => {
+                    // the init value is moved here, which borrowck reports at the field
+                    let mut initvar = initvar.clone();
+                    initvar.set_span(*span);
+                    if initvar == *name {
+                        quote! { #initvar }
+                    } else {
+                        quote! { #initvar.#name }
+                    }
+                }
+                (Some(FieldInit::Child), _) => quote! { #name },
+                _ => quote! { ::core::default::Default::default() },
+            };
+            quote! { #name: #value, }
+        });
+        let tracker = self
+            .tracked()
+            .then(|| quote! { tracker: ::core::default::Default::default(), });
+        quote! { Self { #(#values)* #tracker } }
+    }
+
+    /// The path to the variants of the Output type.
+    fn output_path(&self) -> TokenStream {
+        match &self.out {
+            Output::Enum(..) => format_ident!("{}Output", self.comp).into_token_stream(),
+            Output::Type(ty) => quote! { <#ty> },
+        }
+    }
+
+    /// The visibility of the model, `pub` unless specified.
+    fn default_vis(&self) -> TokenStream {
+        self.model_attrs
+            .vis
+            .as_ref()
+            .map_or_else(|| quote! { pub }, |vis| quote! { #vis })
+    }
+
+    /// The model struct and its `Default` and `Debug` impls.
+    ///
+    /// `Default` is implemented manually for generic components so that it doesn't require the
+    /// generic parameters to implement `Default`, and `Debug` is implemented manually if some
+    /// fields are `#[sensitive]`.
+    fn model_decl(&self) -> TokenStream {
+        let Self {
+            model_attrs,
+            comp,
+            generics,
+            model,
+            sensitive_fields,
+            ..
+        } = self;
+        let (fields, item_generics) = self.model_fields();
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        let decl = fields.as_ref().map_or_else(
+            || quote! { struct #comp #generics #where_clause; },
+            |fields| quote! { struct #comp #generics #where_clause #fields },
+        );
+        let mut derives = vec![];
+
+        let has_defaults =
+            (self.field_inits.iter()).any(|(_, init)| matches!(init, FieldInit::Expr(_)));
+        let default = if !model_attrs.derives("Default")
+            || self.model_expr.is_some()
+            || self.needs_ctor()
+        {
+            None
+        } else if generics.params.is_empty() && !has_defaults {
+            derives.push("Default");
+            None
+        } else {
+            let mut default_generics = generics.clone();
+            let default_where = default_generics.make_where_clause();
+            if !generics.params.is_empty() {
+                for field in fields.iter().flat_map(|f| &f.named) {
+                    let ty = &field.ty;
+                    let has_default = (self.field_inits.iter()).any(|(name, init)| {
+                        field.ident.as_ref() == Some(name) && matches!(init, FieldInit::Expr(_))
+                    });
+                    if !has_default {
+                        default_where
+                            .predicates
+                            .push(syn::parse_quote! { #ty: ::core::default::Default });
+                    }
+                }
+            }
+            let body = self.model_ctor(fields.as_ref(), None);
+            Some(quote! {
+                impl #impl_generics ::core::default::Default for #comp #ty_generics #default_where {
+                    fn default() -> Self {
+                        #body
+                    }
+                }
+            })
+        };
+
+        let debug = if !model_attrs.derives("Debug") {
+            None
+        } else if sensitive_fields.is_empty() {
+            derives.push("Debug");
+            None
+        } else {
+            let debug_fields = model
+                .iter()
+                .flat_map(|f| &f.named)
+                .flat_map(|f| &f.ident)
+                .map(|name| {
+                    if sensitive_fields.contains(name) {
+                        quote! { .field(::core::stringify!(#name), &::kurage::__private::Redacted) }
+                    } else {
+                        quote! { .field(::core::stringify!(#name), &self.#name) }
+                    }
+                });
+            let bounded_where = item_generics.bounded_where(&quote! { ::core::fmt::Debug });
+            Some(quote! {
+                impl #impl_generics ::core::fmt::Debug for #comp #ty_generics #bounded_where {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        f.debug_struct(::core::stringify!(#comp))
+                            #(#debug_fields)*
+                            .finish()
+                    }
+                }
+            })
+        };
+
+        let attrs = model_attrs.to_tokens(&derives, &self.default_vis());
+        quote! {
+            #attrs
+            #decl
+            #default
+            #debug
+        }
+    }
+
+    /// The declaration of the Output enum if any, and the Output type.
+    fn out_decl(&self) -> (Option<TokenStream>, TokenStream) {
+        let (out_attrs, variants) = match &self.out {
+            Output::Enum(out_attrs, variants) => (out_attrs, variants),
+            Output::Type(ty) => return (None, quote! { #ty }),
+        };
+        let out_enum = format_ident!("{}Output", self.comp);
+        let attrs = out_attrs.to_tokens(&["Debug"], &self.default_vis());
+        let item_generics = ItemGenerics::new(&self.generics, variants, false);
+        let generics = &item_generics.generics;
+        let (_, ty_generics, where_clause) = generics.split_for_impl();
+        let phantom = item_generics.phantom_variant().map(|phantom| {
+            let ends_with_comma = variants
+                .clone()
+                .into_iter()
+                .last()
+                .is_none_or(|tt| matches!(tt, TokenTree::Punct(p) if p.as_char() == ','));
+            let sep = (!ends_with_comma).then(|| quote! { , });
+            quote! { #sep #phantom }
+        });
+        (
+            Some(quote! {
+                #attrs
+                enum #out_enum #generics #where_clause {
+                    #variants
+                    #phantom
+                }
+            }),
+            quote! { #out_enum #ty_generics },
+        )
+    }
+
+    pub fn expand(&self) -> TokenStream {
+        let Self {
+            comp,
+            generics,
+            init,
+            update,
+            view,
+            ..
+        } = self;
+
+        let model_decl = self.model_decl();
+        let default_vis = self.default_vis();
+        let (msg_decl, msg_ty, msg_generics) = enum_decl(
+            &format_ident!("{comp}Msg"),
+            (&self.msg_attrs, &default_vis),
+            &["Clone"],
+            &update.handlers,
+            generics,
+        );
+        let (out_decl, out_ty) = self.out_decl();
+        let init_ty = init
+            .as_ref()
+            .and_then(|i| i.init.as_ref())
+            .map_or_else(|| quote! { () }, |(_, ty)| quote! { #ty });
+        let init_fn = self.init_fn();
+        let mangle_initmodel = init.as_ref().map(|InitSection { model, .. }| {
+            quote! { #[::kurage::mangle_ident(#model)] }
+        });
+
+        let (trait_, update_fn, cmd_decl) = self.update_items(&msg_generics);
+        let (hooks, post_view_fn) = self.hooks();
+        let subscriptions_field = (!self.subscriptions.is_empty()).then(subscriptions::field_decl);
+        let reduce_fns = self.reduce_fns(&msg_generics);
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        let inherent_impl = (post_view_fn.is_some() || reduce_fns.is_some()).then(|| {
+            quote! {
+                impl #impl_generics #comp #ty_generics #where_clause {
+                    #reduce_fns
+                    #post_view_fn
+                }
+            }
+        });
+
+        quote! {
+            #model_decl
+            #msg_decl
+            #cmd_decl
+            #out_decl
+
+            // HACK: this ensures `#[watch]` is parsed correctly for `model` idents
+            #[::kurage::mangle_ident(model)]
+            #mangle_initmodel
+            #[::kurage::relm4::component(#default_vis)]
+            impl #impl_generics #trait_ for #comp #ty_generics #where_clause {
+                type Init = #init_ty;
+                type Input = #msg_ty;
+                type Output = #out_ty;
+
+                #init_fn
+
+                #update_fn
+
+                #hooks
+
+                #subscriptions_field
+
+                view! { #view }
+            }
+
+            #inherent_impl
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> GenerateComponent {
+        syn::parse_str(input).expect("valid input")
+    }
+
+    #[test]
+    fn from_init_is_moved_at_the_field() {
+        let comp = parse(
+            "Greeter {
+               #[from_init]
+               name: String,
+             }:
+               init(root, sender, model, widgets) for name: String {}
+               update(self, message, sender) {} => {}
+               gtk::Box {}",
+        );
+        let initvar = comp.init.as_ref().and_then(|init| init.init.as_ref());
+        let ctor = comp.model_ctor(comp.model_fields().0.as_ref(), initvar.map(|(i, _)| i));
+        // `Self { name: name, }`
+        let Some(TokenTree::Group(fields)) = ctor.into_iter().nth(1) else {
+            panic!("expected `Self {{ … }}`");
+        };
+        let Some(TokenTree::Ident(value)) = fields.stream().into_iter().nth(2) else {
+            panic!("expected `name: name`");
+        };
+        assert_eq!(value, "name");
+        let start = value.span().start();
+        assert_eq!((start.line, start.column), (3, 15));
+    }
+
+    /// The message of the error of `input` and the line and column where it starts.
+    fn error(input: &str) -> (String, (usize, usize)) {
+        let Err(e) = syn::parse_str::<GenerateComponent>(input) else {
+            panic!("expected an error");
+        };
+        let start = e.span().start();
+        (e.to_string(), (start.line, start.column))
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("init", "init"), 0);
+        assert_eq!(edit_distance("init", "inti"), 2);
+        assert_eq!(edit_distance("update", "updat"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "trace"), 5);
+    }
+
+    #[test]
+    fn misspelled_section() {
+        assert_eq!(
+            error("Typo: inti(root, sender, model, widgets) {} update(self, message, sender) {}"),
+            (
+                "kurage: unknown section `inti`, did you mean `init`?".to_owned(),
+                (1, 6)
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_section() {
+        let (message, at) = error("Typo: childs { a: A = () } update(self, message, sender) {}");
+        assert_eq!(at, (1, 6));
+        assert_eq!(
+            message,
+            "kurage: unknown section `childs`, expected one of `trace`, `children`, \
+             `subscriptions`, `model`, `preinit`, `init`, `shutdown`, `post_view`, `commands`, \
+             `reduce`, `update`"
+        );
+    }
+
+    #[test]
+    fn missing_update() {
+        assert_eq!(
+            error("NoUpdate: gtk::Box {}"),
+            (
+                "kurage: update() is required before the view".to_owned(),
+                (1, 10)
+            )
+        );
+        assert_eq!(error("NoUpdate:").0, "kurage: update() is required");
+    }
+
+    #[test]
+    fn duplicate_section() {
+        assert_eq!(
+            error(
+                "Twice: preinit {} preinit {} update(self, message, sender) {} => {} gtk::Box {}"
+            ),
+            ("kurage: duplicate section `preinit`".to_owned(), (1, 18))
+        );
+    }
+
+    #[test]
+    fn unused_generics_with_model_expr() {
+        assert_eq!(
+            error(
+                "Holder<'a, T, const N: usize> { s: &'a str }: model = Self { s: \"\" }; \
+                 update(self, message, sender) {} => {} gtk::Box {}"
+            ),
+            (
+                "kurage: with `model = …;`, the fields of the model must use all the generic \
+                 parameters, e.g. in a `PhantomData` field"
+                    .to_owned(),
+                (1, 11)
+            )
+        );
+    }
+
+    #[test]
+    fn bad_output() {
+        assert_eq!(
+            error("BadOutput: update(self, message, sender) {} => 42 gtk::Box {}"),
+            (
+                "kurage: Output block must be `{}` or a type".to_owned(),
+                (1, 47)
+            )
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use proc_macro2::{TokenStream, TokenTree};
+use quote::quote;
+
+/// The name of the hidden variant holding the unused generic parameters of an enum.
+pub const PHANTOM_VARIANT: &str = "__KuragePhantom";
+
+/// Whether `tokens` mention `ident`, or `'ident` if `lifetime` is set.
+fn mentions(tokens: TokenStream, ident: &syn::Ident, lifetime: bool) -> bool {
+    let mut after_quote = false;
+    tokens.into_iter().any(|tt| {
+        let found = match &tt {
+            TokenTree::Group(g) => mentions(g.stream(), ident, lifetime),
+            TokenTree::Ident(i) => i == ident && after_quote == lifetime,
+            _ => false,
+        };
+        after_quote = matches!(&tt, TokenTree::Punct(p) if p.as_char() == '\'');
+        found
+    })
+}
+
+/// Whether `body` mentions `param`.
+fn uses(param: &syn::GenericParam, body: &TokenStream) -> bool {
+    match param {
+        syn::GenericParam::Type(t) => mentions(body.clone(), &t.ident, false),
+        syn::GenericParam::Lifetime(l) => mentions(body.clone(), &l.lifetime.ident, true),
+        syn::GenericParam::Const(c) => mentions(body.clone(), &c.ident, false),
+    }
+}
+
+/// The first type or lifetime parameter of `comp` not mentioned in `body`, which would need a
+/// `PhantomData`.
+pub fn first_unused<'a>(
+    comp: &'a syn::Generics,
+    body: &TokenStream,
+) -> Option<&'a syn::GenericParam> {
+    (comp.params.iter())
+        .find(|param| !matches!(param, syn::GenericParam::Const(_)) && !uses(param, body))
+}
+
+/// The generics of an item declared by the macro.
+///
+/// Enums only take the generic parameters of the component if they mention any of them, in which
+/// case the unused ones are put into a `PhantomData`.
+pub struct ItemGenerics {
+    pub generics: syn::Generics,
+    /// `PhantomData<fn() -> (T, &'a ())>` for the parameters not mentioned in the item.
+    pub phantom: Option<TokenStream>,
+}
+
+impl ItemGenerics {
+    /// The generics of an item containing `body`. If `always` is not set and `body` doesn't
+    /// mention any parameters in `comp`, the item isn't generic.
+    pub fn new(comp: &syn::Generics, body: &TokenStream, always: bool) -> Self {
+        if !always && !comp.params.iter().any(|param| uses(param, body)) {
+            return Self {
+                generics: syn::Generics::default(),
+                phantom: None,
+            };
+        }
+        let unused: Vec<_> = (comp.params.iter())
+            .filter(|param| !uses(param, body))
+            .filter_map(|param| match param {
+                syn::GenericParam::Type(t) => {
+                    let ident = &t.ident;
+                    Some(quote! { #ident })
+                }
+                syn::GenericParam::Lifetime(l) => {
+                    let lt = &l.lifetime;
+                    Some(quote! { &#lt () })
+                }
+                // unused const parameters are fine
+                syn::GenericParam::Const(_) => None,
+            })
+            .collect();
+        Self {
+            generics: comp.clone(),
+            phantom: (!unused.is_empty()).then(|| {
+                quote! { ::core::marker::PhantomData<fn() -> (#(#unused,)*)> }
+            }),
+        }
+    }
+
+    /// The variant to add to an enum if there are unused parameters.
+    pub fn phantom_variant(&self) -> Option<TokenStream> {
+        let variant = quote::format_ident!("{PHANTOM_VARIANT}");
+        self.phantom.as_ref().map(|phantom| {
+            quote! {
+                #[doc(hidden)]
+                #variant(::core::convert::Infallible, #phantom),
+            }
+        })
+    }
+
+    /// The arm matching the phantom variant of `ty`, if any. Set `by_ref` when matching a
+    /// reference.
+    pub fn phantom_arm(&self, ty: &TokenStream, by_ref: bool) -> Option<TokenStream> {
+        let variant = quote::format_ident!("{PHANTOM_VARIANT}");
+        let deref = by_ref.then(|| quote! { * });
+        self.phantom
+            .as_ref()
+            .map(|_| quote! { #ty::#variant(never, _) => match #deref never {}, })
+    }
+
+    /// The where clause of the item with `T: bound` added for each type parameter, like what
+    /// `#[derive]` does.
+    pub fn bounded_where(&self, bound: &TokenStream) -> TokenStream {
+        let mut generics = self.generics.clone();
+        let params: Vec<_> = generics.type_params().map(|t| t.ident.clone()).collect();
+        let where_clause = generics.make_where_clause();
+        for param in params {
+            where_clause
+                .predicates
+                .push(syn::parse_quote! { #param: #bound });
+        }
+        quote! { #where_clause }
+    }
+}
@@ -1,6 +1,15 @@
 use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
 use syn::{parse::Parse, punctuated::Punctuated, Token};
 
+mod attrs;
+mod coalesce;
+mod component;
+mod emit;
+mod generate_component;
+mod generics;
+mod subscriptions;
+mod trace;
+
 struct GenerateGeneratorSyn {
     macroname: syn::Ident,
     component: Option<proc_macro2::TokenTree>,
@@ -37,7 +46,6 @@ impl syn::parse::Parse for GenerateGeneratorSyn {
                         ));
                     }
                     out.component = Some(g.into());
-                    continue;
                 }
                 TokenTree::Group(g) if g.delimiter() == proc_macro2::Delimiter::Brace => {
                     // { structblk }
@@ -121,12 +129,583 @@ fn recurse_replace<'a, T: FnMut(proc_macro2::Ident) -> Box<dyn Iterator<Item = T
     })
 }
 
+/// Generate a [`relm4::SimpleComponent`].
+///
+/// This expands to
+/// - declaration of the model struct (`struct MyLabel { … }`)
+///   - this comes with `#[derive(Debug, Derive)]`
+/// - declaration of `enum MyLabelMsg { … }` for `type Input = MyLabelMsg;`
+/// - impl `fn init()` if the optional `init()` is omitted in macro invocation
+/// - `fn update()` alongside the declaration of the variants in `MyLabelMsg`
+/// - declaration of `enum MyLabelOutput { … }` for `type Output = MyLabelOutput` (unless another
+///   type is specified otherwise)
+/// - declaration of `enum MyLabelCmd { … }` for `type CommandOutput = MyLabelCmd` and
+///   `fn update_cmd()` if the optional `commands { … }` section is given, in which case the macro
+///   implements [`relm4::Component`] instead
+///
+/// Each handler in `update()` and `commands` is written as `Variant => expr`,
+/// `Variant(param: Type, …) => expr` or `Variant { field: Type, … } => expr`, and the parameters
+/// or fields are bound by name in `expr`. Doc comments and other attributes before a handler are
+/// put on its variant; `#[cfg]` also applies to the handler itself.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(Resizer {
+///   size: (i32, i32),
+/// }:
+///   update(self, message, sender) {
+///     /// Resize to `width`×`height`.
+///     Resize { width: i32, height: i32 } => self.size = (width, height),
+///     /// Only available in debug builds.
+///     #[cfg(debug_assertions)]
+///     Dump => println!("{:?}", self.size),
+///   } => {}
+///
+///   gtk::Box {}
+/// );
+///
+/// let msg = ResizerMsg::Resize { width: 800, height: 600 };
+/// ```
+///
+/// `emit Variant(…)` sends `Self::Output::Variant(…)` using the sender of `update()`. It can be the
+/// whole handler, a statement, the value of a `let` or an assignment, an argument or element of a
+/// list, or the body of a closure. Elsewhere, `emit` is left as is. If the output can't be delivered
+/// because the receiver is gone, a warning is logged using `tracing` (when the feature is enabled)
+/// instead of panicking.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// #[derive(Debug)]
+/// pub enum NavAction {
+///   Next,
+///   Back,
+/// }
+///
+/// generate_component!(Wizard {
+///   step: u8,
+/// }:
+///   update(self, message, sender) {
+///     Next => emit Nav(NavAction::Next),
+///     Back => {
+///       self.step = self.step.saturating_sub(1);
+///       emit Nav(NavAction::Back);
+///     }
+///   } => { Nav(NavAction) }
+///
+///   gtk::Box {}
+/// );
+/// ```
+///
+/// With a `reduce` section before `update()`, the handlers of `update()` are moved into
+/// `fn reduce_with(&mut self, message, sender: &MockSender<Self>)`, where the sender is a
+/// [`kurage::reduce::MockSender`] recording the inputs and outputs sent using `input()`,
+/// `output()` and `emit`, and `update()` delegates to it, then delivers the recorded messages in
+/// the order they were sent. `fn reduce(&mut self,
+/// message) -> Vec<Output>` returns the outputs directly, so that the logic of the component can
+/// be tested without a display. Attributes and a visibility may be given before `reduce` for
+/// `fn reduce()`. Other methods of [`relm4::ComponentSender`] aren't available in the handlers.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(Pager {
+///   page: u8,
+/// }:
+///   reduce
+///   update(self, message, sender) {
+///     Next => {
+///       self.page += 1;
+///       emit Moved(self.page);
+///     }
+///   } => { Moved(u8) }
+///
+///   gtk::Box {}
+/// );
+///
+/// let mut pager = Pager::default();
+/// assert!(matches!(pager.reduce(PagerMsg::Next)[..], [PagerOutput::Moved(1)]));
+/// ```
+///
+/// Instead of `Self::default()`, the model may be built by a `model = expr;` clause before
+/// `init()`, which is evaluated before `view_output!()` and may use the `for my_init_var: …` value
+/// of `init()`. `Default` is then not derived for the model, so its fields don't need to implement
+/// `Default`.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(DiskInfo {
+///   path: std::path::PathBuf,
+/// }:
+///   model = Self { path: path.into() };
+///   init(root, sender, model, widgets) for path: String {}
+///   update(self, message, sender) {} => {}
+///
+///   gtk::Label {
+///     set_label: &model.path.display().to_string(),
+///   }
+/// );
+/// ```
+///
+/// Fields of the model may instead have a default value, as in `count: u8 = 1`, which is used by
+/// the generated `Default` impl. Fields marked with `#[from_init]` are taken from the
+/// `for my_init_var: MyInitType` value of `init()`: the value itself if the field has the same
+/// name, otherwise the field of the same name of the value. The model is then constructed from the
+/// init value and the defaults of the other fields, and `Default` is not implemented.
+///
+/// The model is built before `preinit` and the `init()` block run, so the values taken by
+/// `#[from_init]` have been moved into it by then: use the fields of the model instead. The
+/// compiler reports the other uses of the init value as uses of a moved value, which was moved at
+/// the `#[from_init]` field.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(Greeter {
+///   #[from_init]
+///   name: String,
+///   greeting: &'static str = "Hello",
+///   count: u8,
+/// }:
+///   init(root, sender, model, widgets) for name: String {}
+///   update(self, message, sender) {} => {}
+///
+///   gtk::Label {
+///     set_label: &format!("{}, {}!", model.greeting, model.name),
+///   }
+/// );
+/// ```
+///
+/// Child components are declared in a `children { … }` section as
+/// `name: ChildComponent = init_expr`. They are launched in `init()` before the model is built,
+/// with `init_expr` as their `Init`, and stored in the model as [`relm4::Controller`]s. Append
+/// `=> forward |msg| …` to turn the outputs of a child into messages of the component; otherwise
+/// the child is detached. Like with `#[from_init]`, `Default` is not implemented for the model.
+/// The children are available as local variables of the same name to `model = …;`.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(Counter {
+///   value: u8,
+/// }:
+///   update(self, message, sender) {
+///     Increment => {
+///       self.value = self.value.wrapping_add(1);
+///       sender.output(CounterOutput::Changed(self.value)).unwrap();
+///     }
+///   } => { Changed(u8) }
+///
+///   gtk::Button {
+///     set_label: "+",
+///     connect_clicked => Self::Input::Increment,
+///   }
+/// );
+///
+/// generate_component!(Dashboard {
+///   total: u8,
+/// }:
+///   children {
+///     counter: Counter = () => forward |msg| match msg {
+///       CounterOutput::Changed(value) => DashboardMsg::Total(value),
+///     },
+///   }
+///   update(self, message, sender) {
+///     Total(value: u8) => self.total = value,
+///   } => {}
+///
+///   gtk::Box {
+///     append: model.counter.widget(),
+///   }
+/// );
+/// ```
+///
+/// The optional `subscriptions { … }` section, after `children`, sends messages on a timer:
+/// `every 1s => Tick` sends `Tick` every second, and `after 500ms => Ready` sends `Ready` once.
+/// Durations are given in `ms`, `s` or `min`, and the message may take arguments. The timeouts are
+/// registered on the glib main context in `init()`, and removed when the component shuts down (see
+/// [`kurage::subscriptions::Subscriptions`]).
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(Clock {
+///   seconds: u64,
+///   ready: bool,
+/// }:
+///   subscriptions {
+///     every 1s => Tick,
+///     after 500ms => Ready(true),
+///   }
+///   update(self, message, sender) {
+///     Tick => self.seconds += 1,
+///     Ready(ready: bool) => self.ready = ready,
+///   } => {}
+///
+///   gtk::Label {
+///     #[watch]
+///     set_label: &model.seconds.to_string(),
+///     #[watch]
+///     set_visible: model.ready,
+///   }
+/// );
+/// ```
+///
+/// Handlers of `update()` marked with `#[debounce(300ms)]` only run once no other message of
+/// their variant arrived for the given duration, with the latest message, while
+/// `#[throttle(100ms)]` runs the first message right away and then at most one per duration,
+/// again with the latest message. The messages are held back on the glib main context, in a
+/// hidden field of the model (so `model = …;` can't be used), and `reduce()` runs the handlers
+/// right away.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(Finder {
+///   query: String,
+///   offset: f64,
+/// }:
+///   update(self, message, sender) {
+///     #[debounce(300ms)]
+///     Search(query: String) => self.query = query,
+///     #[throttle(100ms)]
+///     Scrolled(offset: f64) => self.offset = offset,
+///   } => {}
+///
+///   gtk::SearchEntry {
+///     connect_search_changed[sender] => move |entry| {
+///       sender.input(FinderMsg::Search(entry.text().into()));
+///     },
+///   }
+/// );
+/// ```
+///
+/// The optional `shutdown(self, widgets, output) { … }` section implements
+/// [`relm4::SimpleComponent::shutdown`] to clean up when the component is destroyed, and
+/// `post_view(self, widgets, sender) { … }` is run at the end of `update_view()`, after the
+/// `#[watch]`ed properties are updated, for changes to the widgets that `#[watch]` can't express.
+/// Both go before `update()`; named widgets are fields of `widgets`.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(Monitor {
+///   changes: u32,
+/// }:
+///   shutdown(self, widgets, output) {
+///     let _ = output.send(MonitorOutput::Stopped(self.changes));
+///   }
+///   post_view(self, widgets, sender) {
+///     if self.changes > 10 {
+///       widgets.count.add_css_class("warning");
+///     }
+///   }
+///   update(self, message, sender) {
+///     Changed => self.changes += 1,
+///   } => { Stopped(u32) }
+///
+///   #[name = "count"]
+///   gtk::Label {
+///     #[watch]
+///     set_label: &model.changes.to_string(),
+///   }
+/// );
+/// ```
+///
+/// Attributes and a visibility may be given before the component name for the model, before
+/// `commands` for `MyLabelCmd`, before `update()` for `MyLabelMsg`, and before the `{ … }` of the
+/// Output for `MyLabelOutput`. The visibility of the model is also the default visibility of the
+/// other types, and is `pub` unless specified. Since leaving it out means the default, a private
+/// item is requested with `pub(self)`. `#[no_derive]` removes the default derives of an
+/// item (`Debug` and `Default` for the model, `Clone` and `Debug` for `MyLabelMsg`, `Debug` for the
+/// others), and `#[no_derive(Default)]` only removes the specified ones, in which case you have to
+/// implement them yourself.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(
+///   #[no_derive(Default)]
+///   pub(crate) Stepper {
+///     step: u8,
+///   }:
+///   #[derive(PartialEq, Eq)]
+///   update(self, message, sender) {
+///     Step => {},
+///   } => #[derive(Clone, Copy)] { Stepped(u8) }
+///
+///   gtk::Box {}
+/// );
+///
+/// impl Default for Stepper {
+///   fn default() -> Self {
+///     Self { step: 1 }
+///   }
+/// }
+/// ```
+///
+/// To only update the widgets whose data changed, put [`#[tracker::track]`](https://docs.rs/tracker)
+/// on the model (this requires a dependency on `tracker`) and use `#[track = "model.changed(…)"]`
+/// instead of `#[watch]` in the view. The tracker is reset at the start of `update()` and
+/// `update_cmd()`, and the `tracker` field is filled in by the generated constructors, but has to
+/// be set by `model = …;` clauses. Children and the hidden fields are not tracked.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(
+///   #[tracker::track]
+///   Playlist {
+///     songs: Vec<String>,
+///     #[do_not_track]
+///     plays: u32,
+///   }:
+///   update(self, message, sender) {
+///     Add(song: String) => self.get_mut_songs().push(song),
+///     Play => self.plays += 1,
+///   } => {}
+///
+///   gtk::Label {
+///     #[track = "model.changed(Playlist::songs())"]
+///     set_label: &model.songs.join("\n"),
+///   }
+/// );
+/// ```
+///
+/// The component name may be followed by generic parameters and a where clause, which are
+/// propagated to the model and the `impl`. The generated enums only take the generic parameters if
+/// their variants mention any of them; unused ones then go into a hidden variant, and likewise a
+/// hidden `PhantomData` field is added to the model if needed. That field can't be set by
+/// `model = …;`, so the fields of the model must then use all the generic parameters themselves,
+/// e.g. in a `PhantomData` field of their own. `Default` of generic models only requires the fields
+/// to implement `Default`.
+///
+/// Message parameters and model fields marked with `#[sensitive]` are printed as `<redacted>` by
+/// the generated `Debug` impls, so that secrets such as passwords don't end up in logs.
+///
+/// With the `tracing` feature of `kurage`, `init()`, `update()` and `update_cmd()` are run inside
+/// a `component` span with the name of the component, the handler and the message variant, and the
+/// time spent is logged when the handler returns. The spans are at the `TRACE` level, unless an
+/// optional `trace(level)` section (one of `trace`, `debug`, `info`, `warn`, `error` and `off`) is
+/// given before all other sections.
+///
+/// Malformed input is reported with errors pointing at the offending tokens, e.g. a misspelt
+/// section name or an `Output` that is neither `{ … }` nor a type.
+///
+/// # Examples
+///
+/// Here is an over-engineered way to show a button labelled "Hello, World!". When the button is
+/// clicked, "Hello, World!" is printed in the console.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(MyLabel:
+///   update(self, message, sender) {} => {}
+///   //                            ┯━    ┬─
+///   //          enum Self::Input ─╯     │
+///   //                                  │
+///   // enum Self::Output (you can also specify your own types)
+///
+///   gtk::Label {
+///     set_label: "Hello, World!",
+///   },
+/// );
+///
+/// generate_component!(MyOtherComponent {
+///   btn: gtk::Button,
+/// }:
+///   children {
+///     mylabel: MyLabel = (),
+///     // ━━━━━━━━━━━━━━━━━ launched with `()` as its Init and stored in `model.mylabel`
+///   }
+///   init[btn](root, sender, model, widgets) /* for my_init_var: MyInitType */ {
+///     //──┬──                               ══════════════════════════════╤══
+///     //  ╰─ optional, a space separated list of things for #[local_ref]  │
+///     //                                                                  ╵
+///     //     when you would like to set anything other than `type Init = ();`
+///     //
+///     // NOTE: init() is entirely optional, but update() is required.
+///     //
+///     // code in this block will be run only after `view_output!()`.
+///     // also, the model (i.e. `self`) is initialized using `Self::default()`, unless a
+///     // `model = …;` clause is given before init().
+///
+///     model.btn.set_tooltip_text(Some("Hi")); // model is mutable
+///   }
+///   update(self, message, sender) {
+///     ButtonClicked => println!("Hello, World!"),
+///   } => {}
+///
+///   gtk::Box {
+///     #[local_ref] btn ->
+///     gtk::Button {
+///       connect_clicked => Self::Input::ButtonClicked,
+///
+///       set_child: Some(model.mylabel.widget()),
+///     }
+///   }
+/// );
+/// ```
+///
+/// Background work can report back to the component using commands. The handlers in `commands`
+/// use the same `self` and `sender` as the ones declared in `update()`.
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(DiskList {
+///   disks: Vec<String>,
+/// }:
+///   trace(debug) // log scans at the DEBUG level
+///   commands {
+///     Scanned(disks: Vec<String>) => self.disks = disks,
+///     //      ━━━━━━━━━━━━━━━━━━ enum Self::CommandOutput, i.e. `DiskListCmd`
+///   }
+///   update(self, message, sender) {
+///     Scan => sender.oneshot_command(async { DiskListCmd::Scanned(vec!["/dev/sda".into()]) }),
+///   } => {}
+///
+///   gtk::Button {
+///     set_label: "Scan",
+///     connect_clicked => Self::Input::Scan,
+///   }
+/// );
+/// ```
+///
+/// Generic components are declared like generic structs:
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// use std::fmt::{Debug, Display};
+///
+/// generate_component!(ListPicker<T> where T: Clone + Debug + Display + 'static {
+///   items: Vec<T>,
+/// }:
+///   init(root, sender, model, widgets) for items: Vec<T> {
+///     model.items = items;
+///   }
+///   update(self, message, sender) {
+///     Pick(index: usize) => {
+///       sender.output(ListPickerOutput::Picked(self.items[index].clone())).unwrap();
+///     },
+///   } => { Picked(T) }
+///
+///   gtk::Label {
+///     #[watch]
+///     set_label: &model.items.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "),
+///   }
+/// );
+///
+/// # fn launch() {
+/// let picker = ListPicker::<u8>::builder().launch(vec![1, 2, 3]).detach();
+/// picker.emit(ListPickerMsg::Pick(0));
+/// # }
+/// ```
+///
+/// Secrets can be kept out of logs using `#[sensitive]`:
+///
+/// ```
+/// use kurage_proc_macros::generate_component;
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(PasswordPage {
+///   #[sensitive]
+///   password: String,
+/// }:
+///   update(self, message, sender) {
+///     SetPassword(#[sensitive] password: String) => self.password = password,
+///   } => {}
+///
+///   gtk::PasswordEntry {
+///     connect_changed[sender] => move |entry| {
+///       sender.input(Self::Input::SetPassword(entry.text().into()));
+///     },
+///   }
+/// );
+///
+/// let msg = PasswordPageMsg::SetPassword("hunter2".into());
+/// assert_eq!(format!("{msg:?}"), "SetPassword(<redacted>)");
+/// ```
+///
+/// # Errors
+///
+/// Mistakes in the sections are reported at the offending token. An unknown section lists the
+/// known ones, or suggests the closest one if it is only a typo away:
+///
+/// ```compile_fail
+/// # use kurage_proc_macros::generate_component;
+/// # use kurage::relm4::prelude::*;
+/// # use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(Typo:
+///   inti(root, sender, model, widgets) {}
+///   // error: kurage: unknown section `inti`, did you mean `init`?
+///   update(self, message, sender) {} => {}
+///
+///   gtk::Box {}
+/// );
+/// ```
+///
+/// A missing `update()`:
+///
+/// ```compile_fail
+/// # use kurage_proc_macros::generate_component;
+/// # use kurage::relm4::prelude::*;
+/// # use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(NoUpdate:
+///   gtk::Box {}
+///   // error: kurage: update() is required before the view
+/// );
+/// ```
+///
+/// An Output that is neither a block of variants nor a type:
+///
+/// ```compile_fail
+/// # use kurage_proc_macros::generate_component;
+/// # use kurage::relm4::prelude::*;
+/// # use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(BadOutput:
+///   update(self, message, sender) {} => 42
+///   // error: kurage: Output block must be `{}` or a type
+///
+///   gtk::Box {}
+/// );
+/// ```
+#[proc_macro]
+pub fn generate_component(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    syn::parse_macro_input!(input as generate_component::GenerateComponent)
+        .expand()
+        .into()
+}
+
 /// Generate a **`macro_rules!`** that has a similar syntax to
 /// [`kurage::generate_component!`], except that the new macro generates components
 /// wrapped in a custom tree of widgets.
 ///
 /// If you don't understand what this means, just look at the example.
 ///
+/// Like in [`kurage::generate_component!`], `init()` of the new macro may be followed by
+/// `for my_init_var: MyInitType`, the fields of the new struct may have defaults or be
+/// `#[from_init]`, and the optional `shutdown()` and `post_view()` sections may be given between
+/// `init()` and `update()`.
+///
 /// The macro accepts an optional argument for the format of the names of the new components
 /// specified in a format accepted by [`paste::paste!`] using the `$name` metavariable.
 ///
@@ -162,7 +741,8 @@ fn recurse_replace<'a, T: FnMut(proc_macro2::Ident) -> Box<dyn Iterator<Item = T
 /// generate_generator! { generate_page => [<$name Page>] {}:
 ///   //                  ━━━━━━━━━┯━━━    ╍╍╍╍╍╍╍╍╍╍╍╍╍╍ ┄┄╍
 ///   //                           │              ┃       ╰─╂── further optional additional fields
-///   //                           │              ┣━━━━━━━━━┛   in the new struct
+///   //                           │              ┣━━━━━━━━━┛   in the new struct, which may
+///   //                           │              ┃             have `= defaults` and `#[from_init]`
 ///   //  required new macro name ─┘     (optional) format of new component names
 ///
 ///   // writing `init: {}` is totally optional
@@ -271,7 +851,16 @@ pub fn generate_generator(input: proc_macro::TokenStream) -> proc_macro::TokenSt
     .collect();
     let component =
         component.unwrap_or_else(|| quote::quote! { [<$name>] }.into_iter().next().unwrap());
-    let structblk = structblk.iter();
+    // the fields of the generator are kept even if the generated macro is given no model
+    let modelblk = structblk.filter(|s| !s.is_empty()).map_or_else(
+        || quote::quote! { $({$($model)+})? },
+        |structblk| {
+            let comma = (structblk.clone().into_iter().last())
+                .is_some_and(|tt| !matches!(&tt, TokenTree::Punct(p) if p.as_char() == ','))
+                .then(|| quote::quote! { , });
+            quote::quote! { {#structblk #comma $($($model)+)?} }
+        },
+    );
     let inputblk = initblk.iter();
     let updateblk = updateblk.iter();
     let updateout = updateout.iter();
@@ -280,23 +869,29 @@ pub fn generate_generator(input: proc_macro::TokenStream) -> proc_macro::TokenSt
             ($name:ident $({$($model:tt)+})? $(as $modelname:ident)?:
                 $(
                 $(preinit $preinit:block)?
-                init$([$($local_ref:tt)+])?($root:ident, $initsender:ident, $initmodel:ident, $initwidgets:ident) $initblock:block
+                init$([$($local_ref:tt)+])?($root:ident, $initsender:ident, $initmodel:ident, $initwidgets:ident)
+                    $(for $init:ident: $inittype:ty)? $initblock:block
                 )?
+                $(shutdown($sdself:ident, $sdwidgets:ident, $sdoutput:ident) $sdblock:block)?
+                $(post_view($pvself:ident, $pvwidgets:ident, $pvsender:ident) $pvblock:block)?
                 update($self:ident, $message:ident, $sender:ident) {
-                    $( $msg:ident$(($($param:ident: $paramtype:ty),+$(,)?))? => $msghdl:expr ),*$(,)?
+                    $($handlers:tt)*
                 }
                 => {$( $out:pat ),*}
                 $($viewtt:tt)*
             ) => { ::kurage::paste::paste! {
                 ::kurage::generate_component!(
-                    #component$({#(#structblk)* $($model)+})? $(as $modelname)?:
-                    $(init$([$($local_ref)+])?($root, $initsender, $initmodel, $initwidgets) {
+                    #component #modelblk $(as $modelname)?:
+                    $(init$([$($local_ref)+])?($root, $initsender, $initmodel, $initwidgets)
+                    $(for $init: $inittype)? {
                         #(#inputblk)*
                         $initblock
                     })?
+                    $(shutdown($sdself, $sdwidgets, $sdoutput) { $sdblock })?
+                    $(post_view($pvself, $pvwidgets, $pvsender) { $pvblock })?
                     update($self, $message, $sender) {
                         #(#updateblk)*
-                        $( $msg$(($($param: $paramtype),+))? => $msghdl),*
+                        $($handlers)*
                     } => {#(#updateout)* $($out),*}
 
                     #view_first #views
@@ -306,6 +901,93 @@ pub fn generate_generator(input: proc_macro::TokenStream) -> proc_macro::TokenSt
     }.into()
 }
 
+/// An attribute alternative to [`kurage::generate_component!`] that is friendlier to IDEs.
+///
+/// Apply this to an `impl MyComponent { … }` block of your model struct. This expands to
+/// - declaration of `enum MyComponentMsg { … }` for `type Input = MyComponentMsg;`, with one
+///   variant per `#[msg]` handler
+/// - `fn update()` that calls the corresponding handler for each message
+/// - declaration of `enum MyComponentOutput { … }` for `type Output = MyComponentOutput` (use
+///   `#[kurage::component(output = { … })]` to add variants, or `output = MyType` to specify your
+///   own type instead)
+/// - impl [`relm4::SimpleComponent`] with everything else in the block (`view!`, `type Init`,
+///   `fn init()`, …)
+///
+/// Handlers are kept as normal methods, so go-to-definition and completion work as usual.
+///
+/// - The variant name is the handler name in `CamelCase`, unless specified using
+///   `#[msg(VariantName)]`.
+/// - Parameters of type `ComponentSender<Self>` receive the sender; all other parameters become
+///   fields of the variant. Mark them with `#[sensitive]` to print them as `<redacted>` in the
+///   `Debug` impl of the input enum.
+/// - If `fn init()` is omitted, the model is initialized using `Self::default()` and
+///   `type Init = ();` unless specified otherwise.
+/// - `fn init()`, whether written or generated, and `fn update()` are traced the same way as in
+///   [`kurage::generate_component!`]; use `#[kurage::component(trace = debug)]` to change the
+///   level of the spans.
+/// - Attributes on the `impl` block are only applied to the inherent impl that keeps the handlers,
+///   not to the [`relm4::SimpleComponent`] impl; put them on the items themselves instead.
+/// - Generic impls like `impl<T: Debug + 'static> MyComponent<T>` are supported, but the
+///   fields of the variants can't use the generic parameters, since the input enum isn't generic.
+///
+/// # Examples
+///
+/// ```
+/// use kurage::relm4::{self, prelude::*};
+/// use kurage::relm4::gtk::{self, prelude::*};
+///
+/// #[derive(Debug, Default)]
+/// pub struct Counter {
+///   value: u8,
+/// }
+///
+/// #[kurage_proc_macros::component(output = { Changed(u8) })]
+/// impl Counter {
+///   /// Add 1 to the counter.
+///   #[msg]
+///   fn increment(&mut self, sender: ComponentSender<Self>) {
+///     //         ━━━━━━━━┯━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+///     // becomes CounterMsg::Increment
+///     self.value = self.value.wrapping_add(1);
+///     sender.output(CounterOutput::Changed(self.value)).unwrap();
+///   }
+///
+///   #[msg(Set)]
+///   fn set_value(&mut self, value: u8) {
+///     // becomes CounterMsg::Set(u8)
+///     self.value = value;
+///   }
+///
+///   view! {
+///     gtk::Box {
+///       gtk::Label {
+///         #[watch]
+///         set_label: &model.value.to_string(),
+///       },
+///       gtk::Button {
+///         set_label: "+",
+///         connect_clicked => CounterMsg::Increment,
+///       },
+///       gtk::Button {
+///         set_label: "Reset",
+///         connect_clicked => CounterMsg::Set(0),
+///       },
+///     }
+///   }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn component(
+    attr: proc_macro::TokenStream,
+    body: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let args = syn::parse_macro_input!(attr as component::ComponentArgs);
+    let item = syn::parse_macro_input!(body as syn::ItemImpl);
+    component::component(args, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 /// # Panics
 /// No.
 #[proc_macro_attribute]
@@ -313,7 +995,8 @@ pub fn mangle_ident(
     attr: proc_macro::TokenStream,
     body: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let ident = syn::parse_macro_input!(attr as syn::Ident);
+    // `parse_any` so that keywords like `self` can also be mangled
+    let ident: syn::Ident = syn::parse_macro_input!(attr with syn::ext::IdentExt::parse_any);
     let mut ii = None;
     let body: TokenStream = recurse_replace(body.into(), &ident.to_string(), &mut |i| {
         if ii.is_none() {
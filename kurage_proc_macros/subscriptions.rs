@@ -0,0 +1,109 @@
+use proc_macro2::{Group, TokenStream};
+use quote::quote;
+use syn::{parse::ParseStream, Token};
+
+/// `every 1s => Variant` or `after 500ms => Variant(…)` in `subscriptions { … }`
+pub struct Subscription {
+    /// `every` or `after`
+    kind: syn::Ident,
+    interval: TokenStream,
+    variant: syn::Ident,
+    args: Option<Group>,
+}
+
+/// Parse a duration such as `500ms`, `1s` or `2min` into a `Duration` expression.
+pub fn parse_duration(input: ParseStream) -> syn::Result<TokenStream> {
+    let lit: syn::LitInt = input.parse()?;
+    let value: u64 = lit.base10_parse()?;
+    let (ctor, value) = match lit.suffix() {
+        "ms" => (quote! { from_millis }, value),
+        "s" => (quote! { from_secs }, value),
+        "min" => (quote! { from_secs }, value.saturating_mul(60)),
+        _ => {
+            return Err(syn::Error::new(
+                lit.span(),
+                "kurage: expected a duration such as `500ms`, `1s` or `2min`",
+            ))
+        }
+    };
+    Ok(quote! { ::std::time::Duration::#ctor(#value) })
+}
+
+impl syn::parse::Parse for Subscription {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let kind: syn::Ident = input.parse()?;
+        if kind != "every" && kind != "after" {
+            return Err(syn::Error::new(
+                kind.span(),
+                "kurage: expected `every` or `after`",
+            ));
+        }
+        let interval = parse_duration(input)?;
+        input.parse::<Token![=>]>()?;
+        let variant = input.parse()?;
+        let args = if input.peek(syn::token::Paren) {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        Ok(Self {
+            kind,
+            interval,
+            variant,
+            args,
+        })
+    }
+}
+
+/// Parse a comma-separated list of subscriptions inside `{ … }`.
+pub fn parse_list(input: ParseStream) -> syn::Result<Vec<Subscription>> {
+    let content;
+    syn::braced!(content in input);
+    let subscriptions =
+        content.parse_terminated(<Subscription as syn::parse::Parse>::parse, Token![,])?;
+    Ok(subscriptions.into_iter().collect())
+}
+
+/// The name of the field of the widgets holding the timeouts.
+pub const FIELD: &str = "kurage_subscriptions";
+
+/// `let kurage_subscriptions = …;` registering the timeouts in `init()`, before `view_output!()`.
+pub fn register(
+    subscriptions: &[Subscription],
+    msg: &syn::Ident,
+    sender: &syn::Ident,
+) -> TokenStream {
+    let field = syn::Ident::new(FIELD, proc_macro2::Span::call_site());
+    let adds = subscriptions.iter().map(
+        |Subscription {
+             kind,
+             interval,
+             variant,
+             args,
+         }| {
+            quote! {
+                #field.#kind(#interval, #sender.input_sender(), || #msg::#variant #args);
+            }
+        },
+    );
+    quote! {
+        let mut #field = ::kurage::subscriptions::Subscriptions::default();
+        #(#adds)*
+    }
+}
+
+/// The field of the widgets holding the timeouts, declared in the trait impl.
+pub fn field_decl() -> TokenStream {
+    let field = syn::Ident::new(FIELD, proc_macro2::Span::call_site());
+    quote! {
+        additional_fields! {
+            #field: ::kurage::subscriptions::Subscriptions,
+        }
+    }
+}
+
+/// Remove the timeouts held by `widgets` in `shutdown()`.
+pub fn clear(widgets: &syn::Ident) -> TokenStream {
+    let field = syn::Ident::new(FIELD, proc_macro2::Span::call_site());
+    quote! { #widgets.#field.clear(); }
+}
@@ -0,0 +1,73 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+const LEVELS: [&str; 6] = ["trace", "debug", "info", "warn", "error", "off"];
+
+/// The level of the spans of a component, written as `trace(level)` or `trace = level`.
+#[derive(Clone)]
+pub struct Level(syn::Ident);
+
+impl syn::parse::Parse for Level {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let ident: syn::Ident = input.parse()?;
+        if !LEVELS.contains(&ident.to_string().as_str()) {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!(
+                    "kurage: unknown trace level `{ident}`, expected one of {}",
+                    LEVELS.map(|s| format!("`{s}`")).join(", ")
+                ),
+            ));
+        }
+        Ok(Self(ident))
+    }
+}
+
+impl quote::ToTokens for Level {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let s = self.0.to_string();
+        let mut chars = s.chars();
+        let variant = chars.next().map_or_else(String::new, |c| {
+            c.to_uppercase().chain(chars).collect::<String>()
+        });
+        let variant = syn::Ident::new(&variant, self.0.span());
+        tokens.extend(quote! { ::kurage::__private::TraceLevel::#variant });
+    }
+}
+
+/// `Some("Variant")` for the variant of `message` (an instance of `ty`), or `None` if there are
+/// no variants. Each variant comes with its `#[cfg]` attributes. `phantom_arm` matches the variant
+/// holding unused generic parameters, if any.
+pub fn variant_name<'a, I: IntoIterator<Item = (TokenStream, &'a syn::Ident)>>(
+    message: &syn::Ident,
+    ty: &TokenStream,
+    variants: I,
+    phantom_arm: Option<&TokenStream>,
+) -> TokenStream {
+    let arms = variants
+        .into_iter()
+        .map(|(cfgs, v)| quote! { #cfgs #ty::#v { .. } => ::core::stringify!(#v), })
+        .collect::<Vec<_>>();
+    if arms.is_empty() {
+        return quote! { ::core::option::Option::None };
+    }
+    quote! { ::core::option::Option::Some(match &#message { #(#arms)* #phantom_arm }) }
+}
+
+/// A `let` statement that keeps a span entered until the end of the current scope.
+pub fn guard(
+    level: Option<&Level>,
+    comp: &syn::Ident,
+    handler: &str,
+    message: &TokenStream,
+) -> TokenStream {
+    let level = level.map_or_else(
+        || quote! { ::kurage::__private::TraceLevel::Trace },
+        |l| quote! { #l },
+    );
+    let comp = comp.to_string();
+    quote! {
+        let _kurage_trace =
+            ::kurage::__private::TraceGuard::new(#level, #comp, #handler, #message);
+    }
+}
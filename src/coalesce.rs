@@ -0,0 +1,176 @@
+//! Coalescing rapid inputs, see `#[debounce]` and `#[throttle]` in [`crate::generate_component!`].
+use kurage_macro_rules::relm4::{gtk::glib, Sender};
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+/// The latest message of a debounced or throttled variant, and the timeout releasing it.
+struct Slot<M> {
+    pending: Option<M>,
+    source: Option<glib::SourceId>,
+    debounce: bool,
+}
+
+/// Holds back the messages of the variants marked with `#[debounce]` or `#[throttle]` until
+/// they should be handled, using timeouts on the glib main context.
+///
+/// Each variant has a slot. When it is time to handle the latest message of a slot, `release(i)`
+/// is sent to the component, which then takes the message back with [`Self::release`].
+///
+/// ```
+/// use kurage::coalesce::Coalesce;
+/// use kurage::relm4::{self, gtk::glib};
+/// use std::time::{Duration, Instant};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Msg {
+///     Search(&'static str),
+///     Release(usize),
+/// }
+///
+/// let (sender, receiver) = relm4::channel();
+/// let coalesce = Coalesce::default();
+/// let context = glib::MainContext::default();
+/// let wait = || while !context.iteration(true) {};
+///
+/// // only the last message comes back, once none arrived for the delay
+/// let delay = Duration::from_millis(50);
+/// assert_eq!(coalesce.debounce(0, Msg::Search("k"), delay, &sender, Msg::Release), None);
+/// let start = Instant::now();
+/// assert_eq!(coalesce.debounce(0, Msg::Search("ku"), delay, &sender, Msg::Release), None);
+/// wait();
+/// assert!(start.elapsed() >= delay);
+/// assert_eq!(receiver.recv_sync(), Some(Msg::Release(0)));
+/// assert_eq!(coalesce.release(0), Some(Msg::Search("ku")));
+///
+/// // the first message is handled right away and the last one at the end of the interval
+/// let interval = Duration::from_millis(50);
+/// let start = Instant::now();
+/// let throttle = |text| coalesce.throttle(1, Msg::Search(text), interval, &sender, Msg::Release);
+/// assert_eq!(throttle("a"), Some(Msg::Search("a")));
+/// assert_eq!(throttle("b"), None);
+/// assert_eq!(throttle("c"), None);
+/// wait();
+/// assert!(start.elapsed() >= interval);
+/// assert_eq!(receiver.recv_sync(), Some(Msg::Release(1)));
+/// assert_eq!(coalesce.release(1), Some(Msg::Search("c")));
+/// // nothing was held back in the interval started by "c"
+/// wait();
+/// assert!(start.elapsed() >= interval * 2);
+/// assert_eq!(throttle("d"), Some(Msg::Search("d")));
+/// ```
+pub struct Coalesce<M> {
+    slots: Rc<RefCell<Vec<Slot<M>>>>,
+}
+
+impl<M> Default for Coalesce<M> {
+    fn default() -> Self {
+        Self {
+            slots: Rc::default(),
+        }
+    }
+}
+
+impl<M> std::fmt::Debug for Coalesce<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let slots = self.slots.borrow();
+        let pending = slots.iter().filter(|slot| slot.pending.is_some()).count();
+        f.debug_struct("Coalesce")
+            .field("pending", &pending)
+            .finish()
+    }
+}
+
+impl<M: 'static> Coalesce<M> {
+    fn with_slot<T>(&self, slot: usize, f: impl FnOnce(&mut Slot<M>) -> T) -> T {
+        let mut slots = self.slots.borrow_mut();
+        if slots.len() <= slot {
+            slots.resize_with(slot + 1, || Slot {
+                pending: None,
+                source: None,
+                debounce: false,
+            });
+        }
+        f(&mut slots[slot])
+    }
+
+    /// Hold `message` back until no other message arrives in its slot for `delay`.
+    ///
+    /// Always returns `None`, the message comes back from [`Self::release`].
+    pub fn debounce<R: Fn(usize) -> M + 'static>(
+        &self,
+        slot: usize,
+        message: M,
+        delay: Duration,
+        sender: &Sender<M>,
+        release: R,
+    ) -> Option<M> {
+        let slots = Rc::clone(&self.slots);
+        let sender = sender.clone();
+        let source = glib::timeout_add_local_once(delay, move || {
+            slots.borrow_mut()[slot].source = None;
+            // the component is gone otherwise
+            let _ = sender.send(release(slot));
+        });
+        self.with_slot(slot, |s| {
+            s.pending = Some(message);
+            s.debounce = true;
+            if let Some(old) = s.source.replace(source) {
+                old.remove();
+            }
+        });
+        None
+    }
+
+    /// Return `message` to be handled right away unless another message of its slot was handled
+    /// in the last `interval`, in which case it is held back until the end of the interval,
+    /// replacing the previous one held back.
+    pub fn throttle<R: Fn(usize) -> M + 'static>(
+        &self,
+        slot: usize,
+        message: M,
+        interval: Duration,
+        sender: &Sender<M>,
+        release: R,
+    ) -> Option<M> {
+        if self.with_slot(slot, |s| s.source.is_some()) {
+            self.with_slot(slot, |s| s.pending = Some(message));
+            return None;
+        }
+        let slots = Rc::clone(&self.slots);
+        let sender = sender.clone();
+        let source = glib::timeout_add_local(interval, move || {
+            let pending = slots.borrow()[slot].pending.is_some();
+            if pending && sender.send(release(slot)).is_ok() {
+                // the released message starts another interval
+                return glib::ControlFlow::Continue;
+            }
+            slots.borrow_mut()[slot].source = None;
+            glib::ControlFlow::Break
+        });
+        self.with_slot(slot, |s| {
+            s.debounce = false;
+            s.source = Some(source);
+        });
+        Some(message)
+    }
+
+    /// Take the message held back in `slot`, if it should be handled now.
+    pub fn release(&self, slot: usize) -> Option<M> {
+        self.with_slot(slot, |s| {
+            if s.debounce && s.source.is_some() {
+                // a newer message restarted the delay
+                return None;
+            }
+            s.pending.take()
+        })
+    }
+}
+
+impl<M> Drop for Coalesce<M> {
+    fn drop(&mut self) {
+        for slot in self.slots.borrow_mut().iter_mut() {
+            if let Some(source) = slot.source.take() {
+                source.remove();
+            }
+        }
+    }
+}
@@ -3,6 +3,10 @@
 //! To get started with 🪼, see:
 //!
 //! - [`generate_component!`]
+//! - [`generate_async_component!`]
+//! - [`generate_factory_component!`]
+//! - [`generate_worker!`]
+//! - [`#[component]`](macro@component)
 //! - [`generate_pages!`]
 //! - [`generate_generator!`]
 //!
@@ -18,6 +22,9 @@
 pub use kurage_macro_rules::*;
 #[doc(inline)]
 pub use kurage_proc_macros::*;
+pub mod coalesce;
 #[cfg(feature = "fluent")]
 pub mod fluent;
+pub mod reduce;
 pub mod shortcuts;
+pub mod subscriptions;
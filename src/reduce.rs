@@ -0,0 +1,144 @@
+//! Testing the state transitions of generated components without GTK.
+//!
+//! See the `reduce` section of [`crate::generate_component!`].
+use kurage_macro_rules::relm4::{Component, ComponentSender};
+use std::cell::RefCell;
+
+/// Stands in for the [`ComponentSender`] in the handlers of a component with a `reduce` section,
+/// recording the messages sent instead of delivering them.
+///
+/// ```
+/// use kurage::generate_component;
+/// use kurage::reduce::{MockSender, Sent};
+/// use kurage::relm4::prelude::*;
+/// use kurage::relm4::gtk::{self, prelude::*};
+/// generate_component!(Counter {
+///   count: u8,
+/// }:
+///   reduce
+///   update(self, message, sender) {
+///     Add(n: u8) => {
+///       self.count += n;
+///       if self.count >= 10 {
+///         sender.input(CounterMsg::Reset);
+///       }
+///       emit Changed(self.count);
+///     }
+///     Reset => self.count = 0,
+///   } => { Changed(u8) }
+///
+///   gtk::Box {}
+/// );
+///
+/// let mut counter = Counter::default();
+/// let sender = MockSender::default();
+/// counter.reduce_with(CounterMsg::Add(7), &sender);
+/// counter.reduce_with(CounterMsg::Add(5), &sender);
+/// assert_eq!(counter.count, 12);
+/// // in the order `update()` delivers them
+/// assert!(matches!(
+///     sender.take_sent()[..],
+///     [
+///         Sent::Output(CounterOutput::Changed(7)),
+///         Sent::Input(CounterMsg::Reset),
+///         Sent::Output(CounterOutput::Changed(12)),
+///     ]
+/// ));
+///
+/// counter.reduce_with(CounterMsg::Add(10), &sender);
+/// let inputs = sender.take_inputs();
+/// assert!(matches!(inputs[..], [CounterMsg::Reset]));
+/// for input in inputs {
+///     counter.reduce_with(input, &sender);
+/// }
+/// assert_eq!(counter.count, 0);
+/// assert!(matches!(sender.take_outputs()[..], [CounterOutput::Changed(22)]));
+/// assert!(sender.take_sent().is_empty());
+/// ```
+pub struct MockSender<C: Component> {
+    sent: RefCell<Vec<Sent<C>>>,
+}
+
+/// A message recorded by a [`MockSender`].
+pub enum Sent<C: Component> {
+    /// Sent by the component to itself.
+    Input(C::Input),
+    /// Sent to the parent, using `output()` or `emit`.
+    Output(C::Output),
+}
+
+impl<C: Component> Default for MockSender<C> {
+    fn default() -> Self {
+        Self {
+            sent: RefCell::default(),
+        }
+    }
+}
+
+impl<C: Component> MockSender<C> {
+    /// Record an input sent by the component to itself.
+    pub fn input(&self, message: C::Input) {
+        self.sent.borrow_mut().push(Sent::Input(message));
+    }
+
+    /// Record an output. Never fails, the `Result` is only there to match
+    /// [`ComponentSender::output`].
+    ///
+    /// # Errors
+    /// None.
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn output(&self, message: C::Output) -> Result<(), C::Output> {
+        self.sent.borrow_mut().push(Sent::Output(message));
+        Ok(())
+    }
+
+    /// Take the messages recorded so far, in the order they were sent.
+    pub fn take_sent(&self) -> Vec<Sent<C>> {
+        self.sent.take()
+    }
+
+    /// Take the inputs recorded so far, keeping the outputs.
+    pub fn take_inputs(&self) -> Vec<C::Input> {
+        self.take_with(|sent| match sent {
+            Sent::Input(message) => Ok(message),
+            sent @ Sent::Output(_) => Err(sent),
+        })
+    }
+
+    /// Take the outputs recorded so far, keeping the inputs.
+    pub fn take_outputs(&self) -> Vec<C::Output> {
+        self.take_with(|sent| match sent {
+            Sent::Output(message) => Ok(message),
+            sent @ Sent::Input(_) => Err(sent),
+        })
+    }
+
+    /// Take the messages `take` returns `Ok` for, keeping the others in order.
+    fn take_with<T, F: FnMut(Sent<C>) -> Result<T, Sent<C>>>(&self, mut take: F) -> Vec<T> {
+        let mut taken = vec![];
+        let kept = (self.sent.take().into_iter())
+            .filter_map(|sent| match take(sent) {
+                Ok(message) => {
+                    taken.push(message);
+                    None
+                }
+                Err(sent) => Some(sent),
+            })
+            .collect();
+        self.sent.replace(kept);
+        taken
+    }
+
+    /// Deliver the recorded messages in order using the real `sender`.
+    #[doc(hidden)]
+    pub fn forward(self, sender: &ComponentSender<C>, component: &'static str) {
+        for sent in self.sent.into_inner() {
+            match sent {
+                Sent::Input(message) => sender.input(message),
+                Sent::Output(message) => {
+                    kurage_macro_rules::__private::emitted(sender.output(message), component);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Timers declared in the `subscriptions` section of [`crate::generate_component!`].
+use kurage_macro_rules::relm4::{gtk::glib, Sender};
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+/// The glib timeouts of a component, removed when cleared or dropped.
+///
+/// The timeouts which are already gone, such as `after` ones which fired, are skipped.
+///
+/// ```
+/// use kurage::relm4::{self, gtk::glib};
+/// use kurage::subscriptions::Subscriptions;
+/// use std::{cell::Cell, rc::Rc, time::Duration};
+///
+/// let (sender, _receiver) = relm4::channel();
+/// let (once, ticks) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+/// let mut subscriptions = Subscriptions::default();
+/// let count = Rc::clone(&once);
+/// subscriptions.after(Duration::from_millis(10), &sender, move || count.set(count.get() + 1));
+/// let count = Rc::clone(&ticks);
+/// subscriptions.every(Duration::from_millis(5), &sender, move || count.set(count.get() + 1));
+///
+/// let context = glib::MainContext::default();
+/// while once.get() == 0 {
+///     context.iteration(true);
+/// }
+/// assert!(ticks.get() >= 1);
+///
+/// // like `shutdown()`, the `after` timeout already fired and must not be removed again
+/// subscriptions.clear();
+/// let ticked = ticks.get();
+/// std::thread::sleep(Duration::from_millis(20));
+/// while context.iteration(false) {}
+/// assert_eq!((once.get(), ticks.get()), (1, ticked));
+/// ```
+#[derive(Default)]
+pub struct Subscriptions {
+    /// The ID of each timeout, taken once it is removed.
+    sources: Vec<Rc<Cell<Option<glib::SourceId>>>>,
+}
+
+impl std::fmt::Debug for Subscriptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let active = (self.sources.iter())
+            .filter(|source| {
+                let id = source.take();
+                let active = id.is_some();
+                source.set(id);
+                active
+            })
+            .count();
+        f.debug_struct("Subscriptions")
+            .field("active", &active)
+            .finish()
+    }
+}
+
+impl Subscriptions {
+    /// Send `message()` to `sender` every `interval`, until cleared or the receiver is gone.
+    pub fn every<M: 'static, F: Fn() -> M + 'static>(
+        &mut self,
+        interval: Duration,
+        sender: &Sender<M>,
+        message: F,
+    ) {
+        self.add(interval, sender, message, glib::ControlFlow::Continue);
+    }
+
+    /// Send `message()` to `sender` once after `delay`, unless cleared before.
+    pub fn after<M: 'static, F: Fn() -> M + 'static>(
+        &mut self,
+        delay: Duration,
+        sender: &Sender<M>,
+        message: F,
+    ) {
+        self.add(delay, sender, message, glib::ControlFlow::Break);
+    }
+
+    fn add<M: 'static, F: Fn() -> M + 'static>(
+        &mut self,
+        interval: Duration,
+        sender: &Sender<M>,
+        message: F,
+        then: glib::ControlFlow,
+    ) {
+        let slot = Rc::new(Cell::new(None));
+        let source = Rc::clone(&slot);
+        let sender = sender.clone();
+        let id = glib::timeout_add_local(interval, move || {
+            let flow = if sender.send(message()).is_ok() {
+                then
+            } else {
+                glib::ControlFlow::Break
+            };
+            if flow == glib::ControlFlow::Break {
+                // removed by glib when returning `Break`
+                source.take();
+            }
+            flow
+        });
+        slot.set(Some(id));
+        self.sources.push(slot);
+    }
+
+    /// Remove all the timeouts.
+    pub fn clear(&mut self) {
+        for source in self.sources.drain(..) {
+            if let Some(id) = source.take() {
+                id.remove();
+            }
+        }
+    }
+}
+
+impl Drop for Subscriptions {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}